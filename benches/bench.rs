@@ -15,6 +15,92 @@ mod benches {
         });
     }
 
+    /// Build a minimal map with large plane, vertex, edge, surface edge and face lumps
+    fn plain_data_map(count: u32) -> Vec<u8> {
+        let mut lumps: Vec<(usize, Vec<u8>)> = Vec::new();
+        let push = |data: &mut Vec<u8>, values: &[u32]| {
+            for value in values {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        };
+
+        // planes: normal, distance and type
+        let mut planes = Vec::new();
+        for i in 0..count {
+            push(
+                &mut planes,
+                &[0, 0, 1f32.to_bits(), (i as f32).to_bits(), 2],
+            );
+        }
+        lumps.push((1, planes));
+        let mut vertices = Vec::new();
+        for i in 0..count {
+            push(&mut vertices, &[(i as f32).to_bits(), 0, 0]);
+        }
+        lumps.push((3, vertices));
+        // a single node splitting into the solid leaf
+        let mut nodes = Vec::new();
+        push(&mut nodes, &[0, -1i32 as u32, -1i32 as u32, 0, 0, 0, 0, 0]);
+        lumps.push((5, nodes));
+        // single edge faces without texture or displacement
+        let mut faces = Vec::new();
+        for i in 0..count {
+            push(&mut faces, &[0, i, 1 | (0xFFFF << 16), u32::MAX]);
+            push(&mut faces, &[0; 10]);
+        }
+        lumps.push((7, faces));
+        lumps.push((10, vec![0; 32]));
+        let mut edges = Vec::new();
+        for i in 0..count {
+            push(
+                &mut edges,
+                &[(i & 0xFFFF) | ((((i + 1) % count) & 0xFFFF) << 16)],
+            );
+        }
+        lumps.push((12, edges));
+        let mut surface_edges = Vec::new();
+        for i in 0..count {
+            push(&mut surface_edges, &[i]);
+        }
+        lumps.push((13, surface_edges));
+        // no game lumps
+        lumps.push((35, vec![0; 4]));
+        // an empty zip for the packfile
+        let mut pack = vec![0x50, 0x4b, 0x05, 0x06];
+        pack.resize(22, 0);
+        lumps.push((40, pack));
+
+        let header_size = 8 + 64 * 16 + 4;
+        let mut directory = vec![[0u32; 4]; 64];
+        let mut data = Vec::new();
+        for (lump, lump_data) in lumps {
+            directory[lump] = [
+                (header_size + data.len()) as u32,
+                lump_data.len() as u32,
+                0,
+                0,
+            ];
+            data.extend_from_slice(&lump_data);
+        }
+
+        let mut map = b"VBSP".to_vec();
+        push(&mut map, &[20]);
+        for entry in directory {
+            push(&mut map, &entry);
+        }
+        push(&mut map, &[1]);
+        map.extend_from_slice(&data);
+        map
+    }
+
+    #[bench]
+    fn from_bytes_plain_data(b: &mut Bencher) {
+        let map = plain_data_map(65536);
+        b.iter(|| {
+            Bsp::read(test::black_box(&map)).unwrap();
+        });
+    }
+
     #[bench]
     fn leaf_at(b: &mut Bencher) {
         let bsp = Bsp::read(MAP_BYTES).unwrap();
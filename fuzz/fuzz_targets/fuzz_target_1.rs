@@ -1,15 +1,80 @@
 #![no_main]
 
 use libfuzzer_sys::fuzz_target;
+use vbsp::{Bsp, ReadOptions, Vector};
 
 fn fuzz(data: &[u8]) {
-    if let Some(bsp) = vbsp::Bsp::read(data).ok() {
+    if let Some(bsp) = Bsp::read(data).ok() {
         let verts: Vec<_> = bsp
             .vertices
             .iter()
             .map(|vertex| vertex.position.x)
             .collect();
         assert!(verts.len() > 1);
+
+        // a successfully read bsp should never cause a handle method to panic
+        walk(&bsp);
+    }
+
+    // the same goes for everything left after a lenient read repaired the bsp
+    if let Ok(bsp) = Bsp::read_with(data, ReadOptions::default().lenient(true)) {
+        walk(&bsp);
+    }
+}
+
+fn walk(bsp: &Bsp) {
+    for cluster in 0..bsp.vis_data.cluster_count.min(i16::MAX as u32) {
+        let _ = bsp.vis_data.visible_clusters(cluster as i16);
+    }
+
+    for leaf in (0..bsp.leaves.len()).filter_map(|i| bsp.leaf(i)) {
+        if let Some(visible) = leaf.visible_set() {
+            visible.count();
+        }
+        leaf.faces().count();
+        leaf.static_props().count();
+    }
+
+    for node in (0..bsp.nodes.len()).filter_map(|i| bsp.node(i)) {
+        node.plane();
+        node.children();
+    }
+
+    for model in bsp.models() {
+        for face in model.faces() {
+            face.texture().name();
+            face.is_visible();
+            face.vertices().count();
+            face.triangulate().count();
+            face.edge_direction();
+        }
+    }
+
+    for displacement in (0..bsp.displacements.len()).filter_map(|i| bsp.displacement(i)) {
+        displacement.displacement_vertices().count();
+        displacement.corner_neighbours().count();
+        displacement.edge_neighbours().count();
+        displacement.triangulated_displaced_vertices().count();
+        displacement.triangles().count();
+        displacement.lightmap_sample_positions().count();
+    }
+
+    for prop in bsp.static_props() {
+        prop.model();
+        prop.leaves().count();
+    }
+
+    for texture in bsp.textures() {
+        texture.name();
+    }
+
+    // a lenient read can leave the bsp without nodes, which is reported as a warning
+    if bsp.try_root_node().is_some() {
+        bsp.leaf_at(Vector {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        });
     }
 }
 
@@ -1,14 +1,16 @@
 use crate::*;
 use binrw::io::Cursor;
 use binrw::BinReaderExt;
+use num_enum::TryFromPrimitive;
 use std::borrow::Cow;
+use std::ops::Range;
 
 pub struct BspFile<'a> {
     data: &'a [u8],
     directories: Directories,
     header: Header,
-    #[allow(dead_code)]
     map_revision: u32,
+    lump_files: &'a [LumpFile],
 }
 
 impl<'a> BspFile<'a> {
@@ -39,6 +41,7 @@ impl<'a> BspFile<'a> {
             directories,
             header,
             map_revision,
+            lump_files: &[],
         })
     }
 
@@ -46,12 +49,69 @@ impl<'a> BspFile<'a> {
         &self.header
     }
 
-    pub fn lump_reader(&self, lump: LumpType) -> BspResult<LumpReader<Cursor<Cow<[u8]>>>> {
+    pub fn directories(&self) -> &Directories {
+        &self.directories
+    }
+
+    pub fn map_revision(&self) -> u32 {
+        self.map_revision
+    }
+
+    /// Replace lumps from the bsp with the contents of lump files
+    pub fn with_lump_files(self, lump_files: &'a [LumpFile]) -> Self {
+        BspFile { lump_files, ..self }
+    }
+
+    fn lump_file(&self, lump: LumpType) -> Option<&'a LumpFile> {
+        // later lump files take precedence
+        self.lump_files
+            .iter()
+            .rev()
+            .find(|file| file.lump() == Some(lump))
+    }
+
+    /// Check if the bsp contains any data for a lump
+    pub fn has_lump(&self, lump: LumpType) -> bool {
+        match self.lump_file(lump) {
+            Some(file) => !file.data.is_empty(),
+            None => self.directories[lump].length > 0,
+        }
+    }
+
+    /// Get the location of a lump in the bsp data
+    ///
+    /// Returns `None` if the lump can't be used directly from the bsp data because it's
+    /// compressed or replaced by a lump file
+    pub fn lump_range(&self, lump_t: LumpType) -> Option<Range<usize>> {
+        if self.lump_file(lump_t).is_some() {
+            return None;
+        }
+        let lump = &self.directories[lump_t];
+        let range = lump.offset as usize..lump.offset as usize + lump.length as usize;
+        (lump.ident == 0 && range.end <= self.data.len()).then_some(range)
+    }
+
+    /// Get the data the offsets of the game lumps point into, along with its position in the file
+    ///
+    /// Game lump offsets are relative to the start of the file containing the game lump, which is
+    /// the `.lmp` file if the game lump is replaced by a lump file
+    pub fn game_lump_data(&self) -> (&'a [u8], i32) {
+        match self.lump_file(LumpType::GameLump) {
+            Some(file) => (&file.data, file.header.offset as i32),
+            None => (self.data, 0),
+        }
+    }
+
+    pub fn lump_reader(&self, lump: LumpType) -> BspResult<LumpReader<Cursor<Cow<'_, [u8]>>>> {
         let (version, data) = self.get_lump(lump)?;
         Ok(LumpReader::new(data, version, lump))
     }
 
-    pub fn get_lump(&self, lump_t: LumpType) -> BspResult<(u32, Cow<[u8]>)> {
+    pub fn get_lump(&self, lump_t: LumpType) -> BspResult<(u32, Cow<'_, [u8]>)> {
+        if let Some(file) = self.lump_file(lump_t) {
+            return Ok((file.header.version, Cow::Borrowed(&file.data)));
+        }
+
         let lump = &self.directories[lump_t];
         let raw_data = self
             .data
@@ -68,9 +128,8 @@ impl<'a> BspFile<'a> {
     }
 }
 
-#[allow(dead_code)]
-#[repr(C)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, TryFromPrimitive)]
 pub enum LumpType {
     Entities,
     Planes,
@@ -146,3 +205,10 @@ pub enum LumpType {
 }
 
 static_assertions::const_assert_eq!(LumpType::DisplacementMultiBlend as usize, 63);
+
+impl LumpType {
+    /// Iterate over all lump types in the order they're stored in the lump directory
+    pub fn all() -> impl Iterator<Item = LumpType> {
+        (0..64).filter_map(|index| LumpType::try_from_primitive(index).ok())
+    }
+}
@@ -1,4 +1,5 @@
 use super::vector::Vector;
+use super::BrushFlags;
 use crate::data::try_read_enum;
 use crate::error::InvalidNeighbourError;
 use binrw::{BinRead, BinResult, Endian};
@@ -39,6 +40,54 @@ impl DisplacementInfo {
     pub fn triangle_count(&self) -> i32 {
         2 * 2i32.pow(self.power as u32).pow(2)
     }
+
+    /// Whether the displacement has multiblend data for its vertices
+    pub fn has_multiblend(&self) -> bool {
+        // newer games store flags in the high bits of the minimum tesselation
+        self.minimum_tesselation & DISPLACEMENT_FLAG_MAGIC != 0
+            && self.minimum_tesselation & DISPLACEMENT_FLAG_HAS_MULTIBLEND != 0
+    }
+}
+
+/// Get the offset of the multiblend data of each displacement
+pub(crate) fn multiblend_offsets(displacements: &[DisplacementInfo]) -> Vec<usize> {
+    displacements
+        .iter()
+        .scan(0, |offset, displacement| {
+            let start = *offset;
+            if displacement.has_multiblend() {
+                *offset += displacement.vertex_count() as usize;
+            }
+            Some(start)
+        })
+        .collect()
+}
+
+const DISPLACEMENT_FLAG_HAS_MULTIBLEND: i32 = 0x40000000;
+const DISPLACEMENT_FLAG_MAGIC: i32 = 0x80000000u32 as i32;
+
+#[test]
+fn test_multiblend_offsets() {
+    use binrw::BinReaderExt;
+    use std::io::Cursor;
+
+    let displacement = |power: i32, multiblend: bool| {
+        let mut data = [0; 176];
+        data[20..24].copy_from_slice(&power.to_le_bytes());
+        if multiblend {
+            let flags = DISPLACEMENT_FLAG_MAGIC | DISPLACEMENT_FLAG_HAS_MULTIBLEND;
+            data[24..28].copy_from_slice(&flags.to_le_bytes());
+        }
+        Cursor::new(data).read_le::<DisplacementInfo>().unwrap()
+    };
+    let displacements = [
+        displacement(2, true),
+        displacement(3, false),
+        displacement(3, true),
+        displacement(2, true),
+    ];
+    assert!(displacements[0].has_multiblend());
+    assert_eq!(vec![0, 25, 25, 106], multiblend_offsets(&displacements));
 }
 
 #[test]
@@ -178,9 +227,9 @@ impl BinRead for NeighbourOrientation {
 
 #[derive(Debug, Clone, BinRead)]
 pub struct DisplacementCornerNeighbour {
-    neighbours: [u16; 4],
+    pub(crate) neighbours: [u16; 4],
     #[br(align_after = align_of::< DisplacementCornerNeighbour > ())]
-    neighbour_count: u8,
+    pub(crate) neighbour_count: u8,
 }
 
 impl DisplacementCornerNeighbour {
@@ -212,14 +261,33 @@ impl DisplacementVertex {
     }
 }
 
+/// Four-way material blend data for a displacement vertex
+#[derive(Debug, Clone, BinRead)]
+pub struct DisplacementMultiBlend {
+    /// Blend weights of the four material layers
+    pub multi_blend: [f32; 4],
+    pub alpha_blend: [f32; 4],
+    /// Tint of each of the four material layers
+    pub colors: [Vector; 4],
+}
+
+static_assertions::const_assert_eq!(size_of::<DisplacementMultiBlend>(), 80);
+
+#[test]
+fn test_multiblend_bytes() {
+    super::test_read_bytes::<DisplacementMultiBlend>();
+}
+
 #[derive(Debug, Clone, BinRead)]
 pub struct DisplacementTriangle {
     pub tags: DisplacementTriangleFlags,
 }
 
+static_assertions::const_assert_eq!(size_of::<DisplacementTriangle>(), 2);
+
 bitflags! {
     #[derive(BinRead)]
-    pub struct DisplacementTriangleFlags: u8 {
+    pub struct DisplacementTriangleFlags: u16 {
         const SURFACE =       0x01;
         const WALKABLE =      0x02;
         const BULDABLE =      0x04;
@@ -227,3 +295,77 @@ bitflags! {
         const SURFACE_PROP2 = 0x10;
     }
 }
+
+/// A triangle of a displacement with its tags
+#[derive(Debug, Clone)]
+pub struct DisplacedTriangle {
+    pub vertices: [Vector; 3],
+    pub tags: DisplacementTriangleFlags,
+    /// Contents of the displacement the triangle is part of
+    pub contents: BrushFlags,
+}
+
+/// Position of a lightmap luxel on a displacement
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplacementLightmapSample {
+    /// Index of the displacement triangle containing the luxel
+    pub triangle: u32,
+    /// Barycentric coordinates of the luxel inside the triangle
+    pub barycentric: [f32; 3],
+}
+
+impl DisplacementLightmapSample {
+    /// Decode a sample from the start of the sample position data
+    ///
+    /// Returns the sample and the number of bytes it used
+    pub(crate) fn decode(data: &[u8]) -> Option<(Self, usize)> {
+        // the triangle index is stored as a sequence of 255 bytes followed by the remainder
+        let mut triangle = 0;
+        let mut used = 0;
+        loop {
+            let byte = *data.get(used)?;
+            used += 1;
+            triangle += byte as u32;
+            if byte != 255 {
+                break;
+            }
+        }
+
+        let coordinates = data.get(used..used + 3)?;
+        let barycentric = [
+            coordinates[0] as f32 / 255.0,
+            coordinates[1] as f32 / 255.0,
+            coordinates[2] as f32 / 255.0,
+        ];
+
+        Some((
+            DisplacementLightmapSample {
+                triangle,
+                barycentric,
+            },
+            used + 3,
+        ))
+    }
+
+    /// Get the position of the luxel in the triangle it's in
+    pub fn position(&self, triangle: &DisplacedTriangle) -> Vector {
+        let [a, b, c] = triangle.vertices;
+        a * self.barycentric[0] + b * self.barycentric[1] + c * self.barycentric[2]
+    }
+}
+
+#[test]
+fn test_lightmap_sample_decode() {
+    let data = [12, 255, 0, 0, 255, 255, 3, 0, 128, 127];
+    let (sample, used) = DisplacementLightmapSample::decode(&data).unwrap();
+    assert_eq!(4, used);
+    assert_eq!(12, sample.triangle);
+    assert_eq!([1.0, 0.0, 0.0], sample.barycentric);
+
+    let (sample, used) = DisplacementLightmapSample::decode(&data[4..]).unwrap();
+    assert_eq!(6, used);
+    assert_eq!(513, sample.triangle);
+    assert_eq!(0.0, sample.barycentric[0]);
+
+    assert!(DisplacementLightmapSample::decode(&data[..3]).is_none());
+}
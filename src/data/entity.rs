@@ -6,7 +6,7 @@ use std::fmt::Debug;
 use std::str::FromStr;
 use vbsp_derive::Entity;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Entities {
     pub entities: String,
 }
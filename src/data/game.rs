@@ -6,8 +6,10 @@ use cgmath::{Deg, Quaternion, Rotation3};
 use std::borrow::Cow;
 use std::io::{Cursor, Read, Seek};
 use std::mem::size_of;
+use std::ops::Range;
+use std::sync::Arc;
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Default, BinRead)]
 pub struct GameLumpHeader {
     pub count: i32,
     #[br(count = count)]
@@ -25,40 +27,153 @@ impl GameLumpHeader {
             .enumerate()
             .find(|(_, lump)| lump.id == T::ID)?;
 
-        let data = match self.get_game_lump_data(i, lump, data) {
+        let data = match self
+            .get_game_lump_raw_data(i, lump, data, 0)
+            .and_then(|raw| decompress_game_lump(raw, lump))
+        {
             Ok(data) => data,
             Err(e) => return Some(Err(e)),
         };
-        let mut reader = Cursor::new(data);
-        Some(reader.read_le_args((lump.version,)).map_err(BspError::from))
+        Some(read_game_lump(data, lump.flags, lump.version))
+    }
+
+    /// Read the data for all game lumps as it's stored in the bsp
+    ///
+    /// The offsets of the game lumps are relative to the start of the file containing them,
+    /// `data_offset` is the position of `data` in that file.
+    /// Compressed lumps are only decompressed when they're used.
+    /// Errors are kept per lump, so a single corrupt lump doesn't prevent reading the others
+    pub fn read_lumps(&self, data: &[u8], data_offset: i32) -> Vec<RawGameLump> {
+        self.lumps
+            .iter()
+            .enumerate()
+            .map(|(i, lump)| RawGameLump {
+                id: lump.id.to_be_bytes(),
+                flags: lump.flags,
+                version: lump.version,
+                length: lump.length,
+                raw_data: self
+                    .get_game_lump_raw_data(i, lump, data, data_offset)
+                    .map(<[u8]>::to_vec)
+                    .map_err(Arc::new),
+            })
+            .collect()
     }
 
-    fn get_game_lump_data<'a>(
+    fn get_game_lump_raw_data<'a>(
         &self,
         i: usize,
         lump: &GameLump,
         data: &'a [u8],
-    ) -> Result<Cow<'a, [u8]>, BspError> {
-        if lump.flags.contains(GameLumpFlags::COMPRESSED) {
+        data_offset: i32,
+    ) -> Result<&'a [u8], BspError> {
+        let size = if lump.flags.contains(GameLumpFlags::COMPRESSED) {
             let next_lump = self
                 .lumps
                 .get(i + 1)
                 .ok_or_else(|| BspError::GameLumpOutOfBounds(lump.clone()))?;
-            let compressed_size = next_lump.offset - lump.offset;
-            let raw_data = data
-                .get(lump.offset as usize..(lump.offset + compressed_size) as usize)
-                .ok_or_else(|| BspError::GameLumpOutOfBounds(lump.clone()))?;
-            let mut output = lzma_decompress_with_header(raw_data, lump.length as usize)?;
-            // some compressed lumps are a bit to small for some reason
-            output.extend_from_slice(&[0; 8]);
-            Ok(Cow::Owned(output))
+            next_lump.offset - lump.offset
         } else {
-            let data = data
-                .get(lump.offset as usize..(lump.offset + lump.length) as usize)
-                .ok_or_else(|| BspError::GameLumpOutOfBounds(lump.clone()))?;
-            Ok(Cow::Borrowed(data))
+            lump.length
+        };
+        let offset = lump.offset - data_offset;
+        data.get(offset as usize..(offset + size) as usize)
+            .ok_or_else(|| BspError::GameLumpOutOfBounds(lump.clone()))
+    }
+}
+
+fn decompress_game_lump<'a>(raw: &'a [u8], lump: &GameLump) -> Result<Cow<'a, [u8]>, BspError> {
+    if lump.flags.contains(GameLumpFlags::COMPRESSED) {
+        Ok(Cow::Owned(lzma_decompress_with_header(
+            raw,
+            lump.length as usize,
+        )?))
+    } else {
+        Ok(Cow::Borrowed(raw))
+    }
+}
+
+fn read_game_lump<T: GameLumpType<Args<'static> = (u16,)>>(
+    data: Cow<[u8]>,
+    flags: GameLumpFlags,
+    version: u16,
+) -> Result<T, BspError> {
+    let data = if flags.contains(GameLumpFlags::COMPRESSED) {
+        // some compressed lumps are a bit to small for some reason
+        let mut data = data.into_owned();
+        data.extend_from_slice(&[0; 8]);
+        Cow::Owned(data)
+    } else {
+        data
+    };
+    let mut reader = Cursor::new(data);
+    reader.read_le_args((version,)).map_err(BspError::from)
+}
+
+/// A game lump as stored in the bsp
+#[derive(Debug, Clone)]
+pub struct RawGameLump {
+    /// The four character id of the game lump, e.g. `b"sprp"` for static props
+    pub id: [u8; 4],
+    pub flags: GameLumpFlags,
+    pub version: u16,
+    /// Size of the lump data after decompression
+    pub length: i32,
+    /// The data as stored in the bsp, still compressed for compressed lumps
+    raw_data: Result<Vec<u8>, Arc<BspError>>,
+}
+
+impl RawGameLump {
+    /// Get the data of the game lump, decompressing it if needed
+    pub fn data(&self) -> Result<Cow<'_, [u8]>, BspError> {
+        match &self.raw_data {
+            Ok(raw) if self.flags.contains(GameLumpFlags::COMPRESSED) => Ok(Cow::Owned(
+                lzma_decompress_with_header(raw, self.length as usize)?,
+            )),
+            Ok(raw) => Ok(Cow::Borrowed(raw)),
+            Err(error) => Err(BspError::GameLump(error.clone())),
         }
     }
+
+    /// Parse the game lump as a specific game lump type
+    ///
+    /// Returns `None` if the id of the lump doesn't match the id of the type
+    pub fn parse<T: GameLumpType<Args<'static> = (u16,)>>(&self) -> Option<Result<T, BspError>> {
+        (i32::from_be_bytes(self.id) == T::ID).then(|| {
+            self.data()
+                .and_then(|data| read_game_lump(data, self.flags, self.version))
+        })
+    }
+}
+
+#[test]
+fn test_raw_game_lump_parse() {
+    let mut lump = RawGameLump {
+        id: *b"sprp",
+        flags: GameLumpFlags::empty(),
+        version: 10,
+        length: 12,
+        raw_data: Ok(vec![0; 12]),
+    };
+    assert!(matches!(lump.data(), Ok(Cow::Borrowed(_))));
+    let props = lump.parse::<PropStaticGameLump>().unwrap().unwrap();
+    assert!(props.props.props.is_empty());
+
+    lump.id = *b"dprp";
+    assert!(lump.parse::<PropStaticGameLump>().is_none());
+
+    lump.id = *b"sprp";
+    lump.flags = GameLumpFlags::COMPRESSED;
+    assert!(matches!(
+        lump.parse::<PropStaticGameLump>(),
+        Some(Err(BspError::LumpDecompressError(_)))
+    ));
+
+    lump.raw_data = Err(Arc::new(BspError::MalformedCompressedGameLump));
+    assert!(matches!(
+        lump.parse::<PropStaticGameLump>(),
+        Some(Err(BspError::GameLump(_)))
+    ));
 }
 
 #[derive(Debug, Clone, BinRead)]
@@ -81,34 +196,99 @@ pub trait GameLumpType: BinRead {
     const ID: i32;
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Default, BinRead)]
 #[br(import(version: u16))]
 pub struct PropStaticGameLump {
     pub dict: StaticPropDictLump,
     pub leaf: StaticPropLeafLump,
     #[br(args(version))]
     pub props: StaticPropLumps,
+    /// Reverse index from leaf to the props contained in it
+    #[br(calc = StaticPropLeafIndex::new(&leaf, &props))]
+    pub leaf_index: StaticPropLeafIndex,
 }
 
 impl GameLumpType for PropStaticGameLump {
     const ID: i32 = i32::from_be_bytes(*b"sprp");
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Default, BinRead)]
 pub struct StaticPropDictLump {
     pub entries: i32,
     #[br(count = entries)]
     pub name: Vec<FixedString<128>>,
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Default, BinRead)]
 pub struct StaticPropLeafLump {
     pub entries: i32,
     #[br(count = entries)]
     pub leaves: Vec<u16>,
 }
 
-#[derive(Debug, Clone, BinRead)]
+/// Reverse index from leaves to the static props in that leaf
+#[derive(Debug, Clone, Default)]
+pub struct StaticPropLeafIndex {
+    props: Vec<Vec<usize>>,
+}
+
+impl StaticPropLeafIndex {
+    pub fn new(leaf: &StaticPropLeafLump, props: &StaticPropLumps) -> Self {
+        let mut index: Vec<Vec<usize>> = Vec::new();
+        for (prop_index, prop) in props.props.iter().enumerate() {
+            for &leaf in leaf.leaves.get(prop.leaf_range()).unwrap_or_default() {
+                let leaf = leaf as usize;
+                if index.len() <= leaf {
+                    index.resize_with(leaf + 1, Vec::new);
+                }
+                index[leaf].push(prop_index);
+            }
+        }
+        StaticPropLeafIndex { props: index }
+    }
+
+    /// Get the indexes of all static props in a leaf
+    pub fn props_in_leaf(&self, leaf: usize) -> &[usize] {
+        self.props.get(leaf).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+#[test]
+fn test_static_prop_leaf_index() {
+    let prop = |first_leaf, leaf_count| StaticPropLump {
+        origin: [0.0; 3].into(),
+        angles: [0.0; 3],
+        prop_type: 0,
+        first_leaf,
+        leaf_count,
+        solid: SolidType::None,
+        skin: 0,
+        fade_min_distance: 0.0,
+        fade_max_distance: 0.0,
+        lighting_origin: [0.0; 3].into(),
+        forced_fade_scale: 0.0,
+        min_dx_level: 0,
+        max_dx_level: 0,
+        flags: StaticPropLumpFlags::empty(),
+        lightmap_resolution: [0; 2],
+    };
+    let leaf = StaticPropLeafLump {
+        entries: 4,
+        leaves: vec![1, 3, 3, 0],
+    };
+    let props = StaticPropLumps {
+        entries: 2,
+        props: vec![prop(0, 2), prop(2, 2)],
+    };
+    let index = StaticPropLeafIndex::new(&leaf, &props);
+    assert_eq!(index.props_in_leaf(0), &[1]);
+    assert_eq!(index.props_in_leaf(1), &[0]);
+    assert!(index.props_in_leaf(2).is_empty());
+    assert_eq!(index.props_in_leaf(3), &[0, 1]);
+    assert!(index.props_in_leaf(4).is_empty());
+}
+
+#[derive(Debug, Clone, Default, BinRead)]
 #[br(import(version: u16))]
 pub struct StaticPropLumps {
     pub entries: i32,
@@ -136,6 +316,12 @@ pub struct StaticPropLump {
 }
 
 impl StaticPropLump {
+    /// Get the range of the prop's entries in the static prop leaf lump
+    pub fn leaf_range(&self) -> Range<usize> {
+        let start = self.first_leaf as usize;
+        start..(start + self.leaf_count as usize)
+    }
+
     /// Get the rotation of the prop as quaternion
     pub fn rotation(&self) -> Quaternion<f32> {
         // angles are applied in roll, pitch, yaw order
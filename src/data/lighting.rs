@@ -0,0 +1,148 @@
+use super::vector::Vector;
+use crate::reader::Version;
+use binrw::{BinRead, BinResult, Endian};
+use bitflags::bitflags;
+use num_enum::TryFromPrimitive;
+use std::io::{Read, Seek};
+
+/// Which variant of the lighting data to load from a bsp
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LightingMode {
+    #[default]
+    Ldr,
+    Hdr,
+}
+
+bitflags! {
+    /// The lighting variants contained in a bsp
+    #[derive(Default)]
+    pub struct LightingModes: u8 {
+        const LDR = 0b01;
+        const HDR = 0b10;
+    }
+}
+
+impl From<LightingMode> for LightingModes {
+    fn from(mode: LightingMode) -> Self {
+        match mode {
+            LightingMode::Ldr => LightingModes::LDR,
+            LightingMode::Hdr => LightingModes::HDR,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorldLight {
+    pub origin: Vector,
+    pub intensity: Vector,
+    pub normal: Vector,
+    /// Offset used for casting shadows, only stored in newer versions of the lump
+    pub shadow_cast_offset: Vector,
+    pub cluster: i32,
+    /// Raw type of the light, use [`emit_type`](Self::emit_type) for the typed value
+    pub emit_type: i32,
+    pub style: i32,
+    pub stop_dot: f32,
+    pub stop_dot2: f32,
+    pub exponent: f32,
+    pub radius: f32,
+    pub constant_attenuation: f32,
+    pub linear_attenuation: f32,
+    pub quadratic_attenuation: f32,
+    pub flags: WorldLightFlags,
+    pub texture_info: i32,
+    pub owner: i32,
+}
+
+impl WorldLight {
+    /// Get the size of a light for a version of the world light lump
+    pub fn size(version: Version) -> usize {
+        if version.0 >= 1 {
+            100
+        } else {
+            88
+        }
+    }
+
+    /// Get the type of light, returns `None` for unknown types
+    pub fn emit_type(&self) -> Option<EmitType> {
+        EmitType::try_from_primitive(self.emit_type).ok()
+    }
+}
+
+impl BinRead for WorldLight {
+    type Args<'a> = (Version,);
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: Endian,
+        (version,): Self::Args<'_>,
+    ) -> BinResult<Self> {
+        let origin = Vector::read_options(reader, endian, ())?;
+        let intensity = Vector::read_options(reader, endian, ())?;
+        let normal = Vector::read_options(reader, endian, ())?;
+        let shadow_cast_offset = if version.0 >= 1 {
+            Vector::read_options(reader, endian, ())?
+        } else {
+            Vector {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        };
+
+        Ok(WorldLight {
+            origin,
+            intensity,
+            normal,
+            shadow_cast_offset,
+            cluster: i32::read_options(reader, endian, ())?,
+            emit_type: i32::read_options(reader, endian, ())?,
+            style: i32::read_options(reader, endian, ())?,
+            stop_dot: f32::read_options(reader, endian, ())?,
+            stop_dot2: f32::read_options(reader, endian, ())?,
+            exponent: f32::read_options(reader, endian, ())?,
+            radius: f32::read_options(reader, endian, ())?,
+            constant_attenuation: f32::read_options(reader, endian, ())?,
+            linear_attenuation: f32::read_options(reader, endian, ())?,
+            quadratic_attenuation: f32::read_options(reader, endian, ())?,
+            flags: WorldLightFlags::read_options(reader, endian, ())?,
+            texture_info: i32::read_options(reader, endian, ())?,
+            owner: i32::read_options(reader, endian, ())?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
+#[repr(i32)]
+pub enum EmitType {
+    Surface = 0,
+    Point = 1,
+    SpotLight = 2,
+    SkyLight = 3,
+    QuakeLight = 4,
+    SkyAmbient = 5,
+}
+
+bitflags! {
+    #[derive(BinRead)]
+    pub struct WorldLightFlags: i32 {
+        const IN_AMBIENT_CUBE = 0x0001;
+    }
+}
+
+#[test]
+fn test_world_light_size() {
+    use binrw::BinReaderExt;
+    use std::io::Cursor;
+
+    for version in [0, 1] {
+        let bytes = [0; 128];
+        let mut reader = Cursor::new(bytes);
+        let _: WorldLight = reader.read_le_args((Version(version),)).unwrap();
+        assert_eq!(
+            WorldLight::size(Version(version)),
+            reader.position() as usize
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use super::LumpEntry;
+use crate::bspfile::LumpType;
+use crate::{BspError, BspResult};
+use binrw::{BinRead, BinReaderExt};
+use num_enum::TryFromPrimitive;
+use std::io::Cursor;
+use std::mem::size_of;
+
+/// Header of a `.lmp` file
+#[derive(Debug, Clone, PartialEq, Eq, BinRead)]
+#[br(little)]
+pub struct LumpFileHeader {
+    /// Offset of the lump data in the file
+    pub offset: u32,
+    pub lump_id: u32,
+    pub version: u32,
+    pub length: u32,
+    /// Revision of the map the lump file was created for
+    pub map_revision: u32,
+}
+
+static_assertions::const_assert_eq!(size_of::<LumpFileHeader>(), 20);
+
+/// A lump stored outside the bsp, replacing the lump from the bsp when loaded
+///
+/// Lump files are stored next to the bsp as `maps/<map>_l_<n>.lmp`.
+#[derive(Debug, Clone)]
+pub struct LumpFile {
+    pub header: LumpFileHeader,
+    pub data: Vec<u8>,
+}
+
+impl LumpFile {
+    /// Create a lump file from the contents of a lump
+    pub fn new(lump: LumpType, version: u32, map_revision: u32, data: Vec<u8>) -> Self {
+        LumpFile {
+            header: LumpFileHeader {
+                offset: size_of::<LumpFileHeader>() as u32,
+                lump_id: lump as u32,
+                version,
+                length: data.len() as u32,
+                map_revision,
+            },
+            data,
+        }
+    }
+
+    /// Read a lump file from the contents of a `.lmp` file
+    pub fn read(data: &[u8]) -> BspResult<Self> {
+        let header: LumpFileHeader = Cursor::new(data).read_le()?;
+        let start = header.offset as usize;
+        let lump_data =
+            data.get(start..start + header.length as usize)
+                .ok_or(BspError::LumpOutOfBounds(LumpEntry {
+                    offset: header.offset,
+                    length: header.length,
+                    version: header.version,
+                    ident: 0,
+                }))?;
+
+        Ok(LumpFile {
+            data: lump_data.into(),
+            header,
+        })
+    }
+
+    /// The lump replaced by the lump file, `None` for unknown lump ids
+    pub fn lump(&self) -> Option<LumpType> {
+        u8::try_from(self.header.lump_id)
+            .ok()
+            .and_then(|id| LumpType::try_from_primitive(id).ok())
+    }
+
+    /// Get the contents of the `.lmp` file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header_size = size_of::<LumpFileHeader>();
+        let mut bytes = Vec::with_capacity(header_size + self.data.len());
+        for field in [
+            header_size as u32,
+            self.header.lump_id,
+            self.header.version,
+            self.data.len() as u32,
+            self.header.map_revision,
+        ] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    /// Get the file name for the nth lump file of a map
+    pub fn file_name(map: &str, index: usize) -> String {
+        format!("{}_l_{}.lmp", map, index)
+    }
+}
+
+#[test]
+fn test_lump_file_round_trip() {
+    let file = LumpFile::new(LumpType::Entities, 0, 12, b"{\n}\n\0".to_vec());
+    let bytes = file.to_bytes();
+    assert_eq!(25, bytes.len());
+
+    let read = LumpFile::read(&bytes).unwrap();
+    assert_eq!(file.header, read.header);
+    assert_eq!(file.data, read.data);
+    assert_eq!(Some(LumpType::Entities), read.lump());
+
+    assert!(LumpFile::read(&bytes[..22]).is_err());
+}
@@ -1,60 +1,89 @@
 mod displacement;
 mod entity;
 mod game;
+mod lighting;
+mod lump_file;
+mod occlusion;
+mod overlay;
+mod physics;
+mod primitive;
 mod vector;
 
 pub use self::displacement::*;
 pub use self::entity::*;
 pub use self::game::*;
+pub use self::lighting::*;
+pub use self::lump_file::*;
+pub use self::occlusion::*;
+pub use self::overlay::*;
+pub use self::physics::*;
+pub use self::primitive::*;
 pub use self::vector::*;
 use crate::bspfile::LumpType;
 use crate::reader::Version;
 use crate::Handle;
-use crate::{BspResult, StringError};
+use crate::{BspError, BspResult, StringError};
 use arrayvec::ArrayString;
 use binrw::error::CustomError;
 use binrw::Endian;
 use binrw::{BinRead, BinResult};
 use bitflags::bitflags;
 use bv::BitVec;
+use bytemuck::{Pod, Zeroable};
 use num_enum::TryFromPrimitive;
 use std::borrow::Cow;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 use std::io::{Cursor, Read, Seek};
 use std::mem::size_of;
-use std::ops::Index;
-use std::sync::Mutex;
+use std::ops::{Index, Range};
+use std::sync::{Arc, Mutex};
 use zip::result::ZipError;
 use zip::ZipArchive;
 
 /// Validate that reading the type consumes `size_of::<T>()` bytes
 #[cfg(test)]
 fn test_read_bytes<T: BinRead>()
+where
+    for<'a> T::Args<'a>: Default,
+{
+    test_read_size::<T>(size_of::<T>())
+}
+
+/// Validate that reading the type consumes `size` bytes
+#[cfg(test)]
+pub(crate) fn test_read_size<T: BinRead>(size: usize)
 where
     for<'a> T::Args<'a>: Default,
 {
     use binrw::BinReaderExt;
     use std::any::type_name;
 
-    let bytes = [0; 512];
+    let bytes = [0; 2048];
     let mut reader = Cursor::new(bytes);
 
     let _ = reader.read_le::<T>().unwrap();
 
     assert_eq!(
         reader.position() as usize,
-        size_of::<T>(),
+        size,
         "Invalid number of bytes used to read {}",
         type_name::<T>()
     );
 }
 
-#[derive(Clone, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 pub struct Directories {
     entries: [LumpEntry; 64],
 }
 
+impl Directories {
+    /// Iterate over the directory entries of all lumps
+    pub fn iter(&self) -> impl Iterator<Item = (LumpType, &LumpEntry)> {
+        LumpType::all().map(|lump| (lump, &self[lump]))
+    }
+}
+
 impl Index<LumpType> for Directories {
     type Output = LumpEntry;
 
@@ -81,11 +110,34 @@ pub struct LumpEntry {
     pub ident: u32,
 }
 
+/// Maps a face to its macro texture
 #[derive(Debug, Clone, BinRead)]
+pub struct FaceMacroTextureInfo {
+    /// Index into the texture string table, `u16::MAX` for faces without macro texture
+    pub texture_string_table_index: u16,
+}
+
+bitflags! {
+    #[derive(Default, BinRead)]
+    pub struct MapFlags: u32 {
+        const BAKED_STATIC_PROP_LIGHTING_NON_HDR = 0x01;
+        const BAKED_STATIC_PROP_LIGHTING_HDR =     0x02;
+        const LIGHTSTYLES_WITH_CSM =               0x04;
+    }
+}
+
+#[derive(Debug, Clone, Copy, BinRead, Pod, Zeroable)]
+#[repr(C)]
 pub struct LeafFace {
     pub face: u16,
 }
 
+/// Maps a face to the id of the face in the hammer map it was compiled from
+#[derive(Debug, Clone, BinRead)]
+pub struct FaceId {
+    pub hammer_face_id: u16,
+}
+
 bitflags! {
     #[derive(BinRead)]
     pub struct TextureFlags: u32 {
@@ -191,7 +243,8 @@ pub struct TextureData {
     pub view_height: i32,
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Copy, BinRead, Pod, Zeroable)]
+#[repr(C)]
 pub struct Plane {
     pub normal: Vector,
     pub dist: f32,
@@ -219,7 +272,8 @@ impl<'a> NodeOrLeaf<'a> {
     }
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Copy, BinRead, Pod, Zeroable)]
+#[repr(C)]
 pub struct Node {
     pub plane_index: i32,
     pub children: [i32; 2],
@@ -308,12 +362,30 @@ impl BinRead for Leaf {
 //     test_read_bytes::<Leaf>();
 // }
 
+#[derive(Debug, Clone, BinRead)]
+pub struct LeafWaterData {
+    /// Height of the water surface
+    pub surface_z: f32,
+    /// Lowest point of the water volume
+    pub min_z: f32,
+    #[br(pad_after = 2)]
+    pub surface_texture_info: i16,
+}
+
+static_assertions::const_assert_eq!(size_of::<LeafWaterData>(), 12);
+
+#[test]
+fn test_leaf_water_data_bytes() {
+    test_read_bytes::<LeafWaterData>();
+}
+
 #[derive(Debug, Clone, BinRead)]
 pub struct LeafBrush {
     pub brush: u16,
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Copy, BinRead, Pod, Zeroable)]
+#[repr(C)]
 pub struct Model {
     pub mins: Vector,
     pub maxs: Vector,
@@ -390,12 +462,14 @@ pub struct BrushSide {
     pub bevel: i16,
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Copy, BinRead, Pod, Zeroable)]
+#[repr(C)]
 pub struct Vertex {
     pub position: Vector,
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Copy, BinRead, Pod, Zeroable)]
+#[repr(C)]
 pub struct Edge {
     pub start_index: u16,
     pub end_index: u16,
@@ -407,9 +481,10 @@ pub enum EdgeDirection {
     LastToFirst,
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Copy, BinRead, Pod, Zeroable)]
+#[repr(C)]
 pub struct SurfaceEdge {
-    edge: i32,
+    pub(crate) edge: i32,
 }
 
 impl SurfaceEdge {
@@ -426,7 +501,8 @@ impl SurfaceEdge {
     }
 }
 
-#[derive(Debug, Clone, BinRead)]
+#[derive(Debug, Clone, Copy, BinRead, Pod, Zeroable)]
+#[repr(C)]
 pub struct Face {
     pub plane_num: u16,
     pub side: u8,
@@ -451,6 +527,13 @@ impl Face {
     pub fn displacement_index(&self) -> Option<i16> {
         (self.displacement_info >= 0).then_some(self.displacement_info)
     }
+
+    /// Get the range of primitives used by the face
+    pub fn primitive_range(&self) -> Range<usize> {
+        // the highest bit is used as the "dynamic shadows enabled" flag
+        let start = self.first_primitive_index as usize;
+        start..(start + (self.primitive_count & 0x7FFF) as usize)
+    }
 }
 
 static_assertions::const_assert_eq!(size_of::<Face>(), 56);
@@ -458,52 +541,151 @@ static_assertions::const_assert_eq!(size_of::<Face>(), 56);
 #[derive(Default, Debug, Clone)]
 pub struct VisData {
     pub cluster_count: u32,
+    /// Offsets of the potentially visible set for each cluster into `data`
+    ///
+    /// The offsets stored in the bsp are relative to the start of the lump, they are rebased
+    /// to start at `data` when reading
     pub pvs_offsets: Vec<i32>,
+    /// Offsets of the potentially audible set for each cluster into `data`, see `pvs_offsets`
     pub pas_offsets: Vec<i32>,
     pub data: Vec<u8>,
 }
 
 impl VisData {
-    pub fn visible_clusters(&self, cluster: i16) -> BitVec<u8> {
-        let offset = if let Some(offset) = self.pvs_offsets.get(cluster as usize) {
-            *offset as usize
-        } else {
-            return BitVec::new();
+    /// Decode the set of clusters potentially visible from a cluster
+    ///
+    /// Returns an empty set for unknown clusters and an error if the run-length encoded data
+    /// extends past the end of the visibility data
+    pub fn visible_clusters(&self, cluster: i16) -> BspResult<BitVec<u8>> {
+        let offset = match usize::try_from(cluster)
+            .ok()
+            .and_then(|cluster| self.pvs_offsets.get(cluster))
+        {
+            Some(offset) => *offset,
+            None => return Ok(BitVec::new()),
         };
+        let out_of_bounds = || BspError::VisDataOutOfBounds {
+            cluster: cluster as usize,
+            offset,
+        };
+        let mut data = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| self.data.get(offset..))
+            .ok_or_else(out_of_bounds)?
+            .iter()
+            .copied();
 
         let mut visible_clusters = BitVec::with_capacity(self.cluster_count as u64);
         visible_clusters.resize(self.cluster_count as u64, false);
 
-        let mut cluster_index = 0;
-        let mut buffer_index = offset;
+        let mut cluster_index: u32 = 0;
 
         while cluster_index < self.cluster_count {
-            if self.data[buffer_index] == 0 {
-                buffer_index += 1;
-                cluster_index += (self.data[buffer_index] as u32) << 3;
-                buffer_index += 1;
-                continue;
+            let byte = data.next().ok_or_else(out_of_bounds)?;
+            if byte == 0 {
+                // a zero byte is followed by the number of invisible clusters, in groups of 8
+                let skip = data.next().ok_or_else(out_of_bounds)?;
+                cluster_index = cluster_index.saturating_add((skip as u32) << 3);
             } else {
                 for i in 0..8 {
                     if self.cluster_count <= cluster_index + i {
                         break;
                     }
 
-                    if (self.data[buffer_index] & (1 << i)) != 0 {
+                    if (byte & (1 << i)) != 0 {
                         visible_clusters.set(cluster_index as u64 + i as u64, true);
                     }
                 }
-                cluster_index += 8;
-                buffer_index += 1;
+                cluster_index = cluster_index.saturating_add(8);
             }
         }
 
-        visible_clusters
+        Ok(visible_clusters)
+    }
+}
+
+#[test]
+fn test_visible_clusters() {
+    let vis_data = |data: Vec<u8>| VisData {
+        cluster_count: 20,
+        pvs_offsets: vec![0, 2, -1],
+        pas_offsets: vec![0, 0, 0],
+        data,
+    };
+    // cluster 0 and 2 visible, skip 8 clusters, cluster 19 visible
+    let visible = vis_data(vec![0b101, 0, 1, 0b1000])
+        .visible_clusters(0)
+        .unwrap();
+    let visible: Vec<u64> = (0..visible.len()).filter(|i| visible[*i]).collect();
+    assert_eq!(vec![0, 2, 19], visible);
+
+    assert!(vis_data(vec![0b101, 0, 1]).visible_clusters(0).is_err());
+    assert!(vis_data(vec![0b101, 0]).visible_clusters(1).is_err());
+    assert!(vis_data(vec![0b101, 0, 1, 0b1000])
+        .visible_clusters(2)
+        .is_err());
+    assert_eq!(0, vis_data(Vec::new()).visible_clusters(3).unwrap().len());
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct CubeMap {
+    pub origin: [i32; 3],
+    /// Size of the cubemap texture as `2^(size - 1)` pixels, or 0 for the default size
+    pub size: i32,
+}
+
+impl CubeMap {
+    pub fn position(&self) -> Vector {
+        Vector {
+            x: self.origin[0] as f32,
+            y: self.origin[1] as f32,
+            z: self.origin[2] as f32,
+        }
+    }
+
+    /// Get the width and height of the cubemap texture in pixels, `None` if the default size is used
+    pub fn texture_size(&self) -> Option<u32> {
+        (self.size > 0).then(|| 1u32.checked_shl(self.size as u32 - 1).unwrap_or(u32::MAX))
+    }
+}
+
+static_assertions::const_assert_eq!(size_of::<CubeMap>(), 16);
+
+#[test]
+fn test_cubemap_texture_size() {
+    let cubemap = |size| CubeMap {
+        origin: [0; 3],
+        size,
+    };
+    assert_eq!(None, cubemap(0).texture_size());
+    assert_eq!(Some(1), cubemap(1).texture_size());
+    assert_eq!(Some(32), cubemap(6).texture_size());
+}
+
+/// Data shared between the bsp and the caller, for example a memory mapped file
+pub type SharedData = Arc<dyn AsRef<[u8]> + Send + Sync>;
+
+/// Contents of the packfile, either owned or a range of a shared buffer
+#[derive(Clone)]
+pub enum PackfileData {
+    Owned(Vec<u8>),
+    Shared {
+        data: SharedData,
+        range: Range<usize>,
+    },
+}
+
+impl AsRef<[u8]> for PackfileData {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            PackfileData::Owned(data) => data,
+            PackfileData::Shared { data, range } => &data.as_ref().as_ref()[range.clone()],
+        }
     }
 }
 
 pub struct Packfile {
-    pub zip: Mutex<ZipArchive<Cursor<Vec<u8>>>>,
+    pub zip: Mutex<ZipArchive<Cursor<PackfileData>>>,
 }
 
 impl Clone for Packfile {
@@ -531,10 +713,27 @@ impl Debug for Packfile {
     }
 }
 
+impl Default for Packfile {
+    fn default() -> Self {
+        // a zip file without any entries only consists of the end of central directory record
+        let mut empty = vec![0; 22];
+        empty[0..4].copy_from_slice(b"PK\x05\x06");
+        Packfile::from_data(PackfileData::Owned(empty)).expect("empty zip is valid")
+    }
+}
+
 impl Packfile {
     pub fn read(data: Cow<[u8]>) -> BspResult<Self> {
-        let reader = Cursor::new(data.into_owned());
-        let zip = Mutex::new(ZipArchive::new(reader)?);
+        Self::from_data(PackfileData::Owned(data.into_owned()))
+    }
+
+    /// Read the packfile from a range of a shared buffer without copying it
+    pub fn read_shared(data: SharedData, range: Range<usize>) -> BspResult<Self> {
+        Self::from_data(PackfileData::Shared { data, range })
+    }
+
+    fn from_data(data: PackfileData) -> BspResult<Self> {
+        let zip = Mutex::new(ZipArchive::new(Cursor::new(data))?);
         Ok(Packfile { zip })
     }
 
@@ -554,6 +753,19 @@ impl Packfile {
         Ok(Some(buff))
     }
 
+    /// Get the name of the map the packfile was built for
+    ///
+    /// Derived from the `materials/maps/<map>/` directory the cubemaps and patched materials
+    /// are packed into, `None` if the packfile contains no such files
+    pub fn map_name(&self) -> Option<String> {
+        let zip = self.zip.lock().unwrap();
+        let name = zip.file_names().find_map(|name| {
+            let (map, _) = name.strip_prefix("materials/maps/")?.split_once('/')?;
+            Some(map.to_string())
+        });
+        name
+    }
+
     pub fn contains(&self, name: &str) -> BspResult<bool> {
         let mut zip = self.zip.lock().unwrap();
         let contains = zip.by_name(name).is_ok();
@@ -0,0 +1,115 @@
+use super::vector::Vector;
+use crate::reader::Version;
+use binrw::{BinRead, BinResult, Endian};
+use bitflags::bitflags;
+use std::io::{Read, Seek};
+use std::mem::size_of;
+
+/// Occluders from func_occluder entities
+#[derive(Debug, Clone, Default, BinRead)]
+#[br(import(version: Version))]
+pub struct Occlusion {
+    pub occluder_count: i32,
+    #[br(args { count: occluder_count as usize, inner: (version,) })]
+    pub occluders: Vec<Occluder>,
+    pub polygon_count: i32,
+    #[br(count = polygon_count)]
+    pub polygons: Vec<OccluderPolygon>,
+    pub vertex_index_count: i32,
+    #[br(count = vertex_index_count)]
+    pub vertex_indices: Vec<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Occluder {
+    pub flags: OccluderFlags,
+    pub first_polygon: i32,
+    pub polygon_count: i32,
+    pub mins: Vector,
+    pub maxs: Vector,
+    /// The area the occluder is in, only available in version 2 and up of the occlusion lump
+    pub area: i32,
+}
+
+impl BinRead for Occluder {
+    type Args<'a> = (Version,);
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: Endian,
+        (version,): Self::Args<'_>,
+    ) -> BinResult<Self> {
+        let flags = OccluderFlags::read_options(reader, endian, ())?;
+        let first_polygon = i32::read_options(reader, endian, ())?;
+        let polygon_count = i32::read_options(reader, endian, ())?;
+        let mins = Vector::read_options(reader, endian, ())?;
+        let maxs = Vector::read_options(reader, endian, ())?;
+        let area = if version.0 >= 2 {
+            i32::read_options(reader, endian, ())?
+        } else {
+            0
+        };
+
+        Ok(Occluder {
+            flags,
+            first_polygon,
+            polygon_count,
+            mins,
+            maxs,
+            area,
+        })
+    }
+}
+
+static_assertions::const_assert_eq!(size_of::<Occluder>(), 40);
+
+impl Occluder {
+    pub fn is_active(&self) -> bool {
+        !self.flags.contains(OccluderFlags::INACTIVE)
+    }
+}
+
+bitflags! {
+    #[derive(BinRead)]
+    pub struct OccluderFlags: i32 {
+        const INACTIVE = 0x1;
+    }
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct OccluderPolygon {
+    pub first_vertex_index: i32,
+    pub vertex_count: i32,
+    pub plane: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<OccluderPolygon>(), 12);
+
+#[test]
+fn test_occlusion_versions() {
+    use binrw::BinReaderExt;
+    use std::io::Cursor;
+
+    let lump = |area: Option<i32>| {
+        let mut data = Vec::new();
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&[0; 36]);
+        if let Some(area) = area {
+            data.extend_from_slice(&area.to_le_bytes());
+        }
+        data.extend_from_slice(&[0; 8]);
+        data
+    };
+
+    let v1: Occlusion = Cursor::new(lump(None)).read_le_args((Version(1),)).unwrap();
+    assert_eq!(1, v1.occluders.len());
+    assert_eq!(0, v1.occluders[0].area);
+    assert!(v1.polygons.is_empty());
+
+    let v2: Occlusion = Cursor::new(lump(Some(7)))
+        .read_le_args((Version(2),))
+        .unwrap();
+    assert_eq!(1, v2.occluders.len());
+    assert_eq!(7, v2.occluders[0].area);
+    assert!(v2.polygons.is_empty());
+}
@@ -0,0 +1,109 @@
+use super::vector::Vector;
+use binrw::BinRead;
+use std::mem::size_of;
+
+pub const OVERLAY_FACE_COUNT: usize = 64;
+pub const WATER_OVERLAY_FACE_COUNT: usize = 256;
+
+pub type Overlay = GenericOverlay<OVERLAY_FACE_COUNT>;
+pub type WaterOverlay = GenericOverlay<WATER_OVERLAY_FACE_COUNT>;
+
+/// An overlay (info_overlay or info_overlay_water) projected onto a set of faces
+#[derive(Debug, Clone, BinRead)]
+pub struct GenericOverlay<const FACE_COUNT: usize> {
+    pub id: i32,
+    pub texture_info: i16,
+    /// Face count in the lower 14 bits, render order in the upper 2 bits
+    pub face_count_and_render_order: u16,
+    pub face_indices: [i32; FACE_COUNT],
+    pub u: [f32; 2],
+    pub v: [f32; 2],
+    /// Corners of the overlay in the overlay plane, the `z` components store the u basis axis
+    pub uv_points: [Vector; 4],
+    pub origin: Vector,
+    pub basis_normal: Vector,
+}
+
+static_assertions::const_assert_eq!(size_of::<Overlay>(), 352);
+static_assertions::const_assert_eq!(size_of::<WaterOverlay>(), 1120);
+
+impl<const FACE_COUNT: usize> GenericOverlay<FACE_COUNT> {
+    pub fn face_count(&self) -> usize {
+        (self.face_count_and_render_order & 0x3FFF) as usize
+    }
+
+    pub fn render_order(&self) -> u8 {
+        (self.face_count_and_render_order >> 14) as u8
+    }
+
+    /// Get the indexes of the faces the overlay is projected on
+    pub fn faces(&self) -> &[i32] {
+        &self.face_indices[..self.face_count().min(FACE_COUNT)]
+    }
+
+    /// Get the u, v and normal axis of the overlay plane
+    pub fn basis(&self) -> [Vector; 3] {
+        let u = Vector {
+            x: self.uv_points[0].z,
+            y: self.uv_points[1].z,
+            z: self.uv_points[2].z,
+        };
+        let normal = self.basis_normal;
+        [u, normal.cross(&u), normal]
+    }
+
+    /// Get the corners of the overlay in the overlay plane
+    pub fn corners_2d(&self) -> [[f32; 2]; 4] {
+        self.uv_points.map(|point| [point.x, point.y])
+    }
+
+    /// Get the world space corners of the unprojected overlay
+    pub fn corners(&self) -> [Vector; 4] {
+        let [u, v, _] = self.basis();
+        self.corners_2d().map(|[x, y]| self.origin + u * x + v * y)
+    }
+
+    /// Get the texture coordinates of the corners of the overlay
+    pub fn texture_coordinates(&self) -> [[f32; 2]; 4] {
+        [
+            [self.u[0], self.v[0]],
+            [self.u[0], self.v[1]],
+            [self.u[1], self.v[1]],
+            [self.u[1], self.v[0]],
+        ]
+    }
+}
+
+#[test]
+fn test_overlay_bytes() {
+    super::test_read_bytes::<Overlay>();
+    super::test_read_bytes::<WaterOverlay>();
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct OverlayFade {
+    pub min_distance_squared: f32,
+    pub max_distance_squared: f32,
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct OverlaySystemLevel {
+    pub min_cpu_level: u8,
+    pub max_cpu_level: u8,
+    pub min_gpu_level: u8,
+    pub max_gpu_level: u8,
+}
+
+/// An overlay clipped to and projected onto one of its faces
+#[derive(Debug, Clone)]
+pub struct OverlayPolygon {
+    /// Index of the face the polygon lies on
+    pub face: usize,
+    pub vertices: Vec<OverlayVertex>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OverlayVertex {
+    pub position: Vector,
+    pub uv: [f32; 2],
+}
@@ -0,0 +1,338 @@
+use super::vector::Vector;
+use crate::BspResult;
+use binrw::{BinRead, BinReaderExt, BinResult};
+use std::io::{Cursor, Seek, SeekFrom};
+
+#[derive(Debug, Clone, BinRead)]
+pub struct PhysicsModelHeader {
+    pub model_index: i32,
+    pub data_size: i32,
+    pub key_data_size: i32,
+    pub solid_count: i32,
+}
+
+/// Collision data for a brush model
+#[derive(Debug, Clone)]
+pub struct PhysicsModel {
+    /// Index of the model in the bsp's models
+    pub model_index: i32,
+    pub solids: Vec<PhysicsSolid>,
+    /// Key values text describing the solids of the model
+    pub key_values: String,
+}
+
+impl PhysicsModel {
+    /// Get the key value blocks describing the model's solids, such as `solid` and `editparams`
+    pub fn key_value_blocks(&self) -> Vec<KeyValueBlock<'_>> {
+        parse_key_value_blocks(&self.key_values)
+    }
+
+    /// Get the properties of a solid from the key values
+    pub fn solid_properties(&self, solid: usize) -> Option<KeyValueBlock<'_>> {
+        self.key_value_blocks()
+            .into_iter()
+            .filter(|block| block.name == "solid")
+            .find(|block| block.get("index").and_then(|index| index.parse().ok()) == Some(solid))
+    }
+
+    /// Get the surface property of a solid, such as "metal" or "default"
+    pub fn surface_prop(&self, solid: usize) -> Option<&str> {
+        self.solid_properties(solid)?.get("surfaceprop")
+    }
+}
+
+/// Compact collision surface of a solid, as stored by vphysics
+#[derive(Debug, Clone)]
+pub struct PhysicsSolid {
+    pub data: Vec<u8>,
+}
+
+/// A convex part of a collision solid
+#[derive(Debug, Clone, Default)]
+pub struct ConvexHull {
+    pub vertices: Vec<Vector>,
+    /// Triangles making up the hull surface, as indexes into `vertices`
+    pub triangles: Vec<[u16; 3]>,
+}
+
+const VPHYSICS_ID: [u8; 4] = *b"VPHY";
+const SURFACE_HEADER_SIZE: u64 = 28;
+const LEDGE_TREE_NODE_SIZE: u64 = 28;
+const LEDGE_SIZE: u64 = 16;
+const TRIANGLE_SIZE: u64 = 16;
+const POINT_SIZE: u64 = 16;
+/// ivp uses meters
+const INCHES_PER_METER: f32 = 1.0 / 0.0254;
+
+impl PhysicsSolid {
+    /// Decode the convex hulls making up the solid
+    pub fn hulls(&self) -> BspResult<Vec<ConvexHull>> {
+        Ok(self.read_hulls()?)
+    }
+
+    fn read_hulls(&self) -> BinResult<Vec<ConvexHull>> {
+        let mut reader = Cursor::new(self.data.as_slice());
+
+        let surface_start = if self.data.get(0..4) == Some(&VPHYSICS_ID[..]) {
+            reader.seek(SeekFrom::Start(4))?;
+            let version: i16 = reader.read_le()?;
+            let model_type: i16 = reader.read_le()?;
+            if version != 0x100 || model_type != 0 {
+                return Err(binrw::Error::AssertFail {
+                    pos: 4,
+                    message: format!(
+                        "unsupported collision model version {} type {}",
+                        version, model_type
+                    ),
+                });
+            }
+            SURFACE_HEADER_SIZE
+        } else {
+            // legacy solids contain the compact surface directly
+            0
+        };
+
+        // skip the mass center, inertia, radius and size of the compact surface
+        reader.seek(SeekFrom::Start(surface_start + 32))?;
+        let ledge_tree_offset: i32 = reader.read_le()?;
+
+        let mut hulls = Vec::new();
+        let mut nodes = vec![offset(surface_start, ledge_tree_offset)?];
+        while let Some(node) = nodes.pop() {
+            if nodes.len() + hulls.len() > self.data.len() {
+                return Err(binrw::Error::AssertFail {
+                    pos: node,
+                    message: "cyclic ledge tree".into(),
+                });
+            }
+            reader.seek(SeekFrom::Start(node))?;
+            let right_offset: i32 = reader.read_le()?;
+            let ledge_offset: i32 = reader.read_le()?;
+
+            if right_offset == 0 {
+                hulls.push(read_ledge(&mut reader, offset(node, ledge_offset)?)?);
+            } else {
+                nodes.push(offset(node, right_offset)?);
+                nodes.push(node + LEDGE_TREE_NODE_SIZE);
+            }
+        }
+
+        Ok(hulls)
+    }
+}
+
+fn offset(base: u64, offset: i32) -> BinResult<u64> {
+    base.checked_add_signed(offset as i64)
+        .ok_or(binrw::Error::AssertFail {
+            pos: base,
+            message: format!("invalid offset {}", offset),
+        })
+}
+
+fn read_ledge(reader: &mut Cursor<&[u8]>, ledge: u64) -> BinResult<ConvexHull> {
+    reader.seek(SeekFrom::Start(ledge))?;
+    let point_offset: i32 = reader.read_le()?;
+    let _client_data: i32 = reader.read_le()?;
+    let _flags: u32 = reader.read_le()?;
+    let triangle_count: i16 = reader.read_le()?;
+    let points = offset(ledge, point_offset)?;
+
+    let mut hull = ConvexHull::default();
+    let mut point_indices: Vec<u16> = Vec::new();
+
+    for triangle in 0..triangle_count.max(0) as u64 {
+        // skip the triangle index and material
+        reader.seek(SeekFrom::Start(
+            ledge + LEDGE_SIZE + triangle * TRIANGLE_SIZE + 4,
+        ))?;
+        let edges: [u32; 3] = reader.read_le()?;
+        let mut indices = [0; 3];
+        for (index, edge) in indices.iter_mut().zip(edges) {
+            let point = (edge & 0xFFFF) as u16;
+            *index = match point_indices.iter().position(|existing| *existing == point) {
+                Some(index) => index as u16,
+                None => {
+                    point_indices.push(point);
+                    (point_indices.len() - 1) as u16
+                }
+            };
+        }
+        hull.triangles.push(indices);
+    }
+
+    for point in point_indices {
+        reader.seek(SeekFrom::Start(points + point as u64 * POINT_SIZE))?;
+        let [x, y, z]: [f32; 3] = reader.read_le()?;
+        // ivp uses a y-down coordinate system
+        hull.vertices.push(Vector {
+            x: x * INCHES_PER_METER,
+            y: z * INCHES_PER_METER,
+            z: -y * INCHES_PER_METER,
+        });
+    }
+
+    Ok(hull)
+}
+
+/// A block in the key values text of a physics model
+#[derive(Debug, Clone)]
+pub struct KeyValueBlock<'a> {
+    pub name: &'a str,
+    pub properties: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> KeyValueBlock<'a> {
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.properties
+            .iter()
+            .find_map(|(property, value)| (*property == key).then_some(*value))
+    }
+}
+
+fn parse_key_value_blocks(text: &str) -> Vec<KeyValueBlock<'_>> {
+    let mut tokens = KeyValueTokens { text };
+    let mut blocks = Vec::new();
+    let mut name = None;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "{" => {
+                let mut block = KeyValueBlock {
+                    name: name.take().unwrap_or_default(),
+                    properties: Vec::new(),
+                };
+                let mut depth = 1;
+                let mut key = None;
+                for token in tokens.by_ref() {
+                    match token {
+                        "{" => depth += 1,
+                        "}" => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        // only properties directly in the block are kept
+                        token if depth == 1 => match key.take() {
+                            Some(key) => block.properties.push((key, token)),
+                            None => key = Some(token),
+                        },
+                        _ => {}
+                    }
+                }
+                blocks.push(block);
+            }
+            token => name = Some(token),
+        }
+    }
+
+    blocks
+}
+
+struct KeyValueTokens<'a> {
+    text: &'a str,
+}
+
+impl<'a> Iterator for KeyValueTokens<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.text = self
+            .text
+            .trim_start_matches(|c: char| c.is_whitespace() || c == '\0');
+        let mut chars = self.text.char_indices();
+        let (_, first) = chars.next()?;
+        let (token, rest) = match first {
+            '{' | '}' => self.text.split_at(1),
+            '"' => {
+                let end = self.text[1..]
+                    .find('"')
+                    .map_or(self.text.len(), |end| end + 1);
+                let token = &self.text[1..end];
+                (token, self.text.get(end + 1..).unwrap_or_default())
+            }
+            _ => {
+                let end = self
+                    .text
+                    .find(|c: char| c.is_whitespace() || c == '{' || c == '}' || c == '"')
+                    .unwrap_or(self.text.len());
+                self.text.split_at(end)
+            }
+        };
+        self.text = rest;
+        Some(token)
+    }
+}
+
+#[test]
+fn test_key_values() {
+    let model = PhysicsModel {
+        model_index: 0,
+        solids: Vec::new(),
+        key_values: r#"solid {
+"index" "0"
+"mass" "5000.000000"
+"surfaceprop" "metal"
+}
+solid {
+"index" "1"
+"surfaceprop" "wood"
+}
+editparams {
+"totalmass" "100"
+"nested" { "key" "value" }
+}
+"#
+        .into(),
+    };
+    assert_eq!(3, model.key_value_blocks().len());
+    assert_eq!(Some("metal"), model.surface_prop(0));
+    assert_eq!(Some("wood"), model.surface_prop(1));
+    assert_eq!(None, model.surface_prop(2));
+    assert_eq!(Some("100"), model.key_value_blocks()[2].get("totalmass"));
+    assert_eq!(None, model.key_value_blocks()[2].get("key"));
+}
+
+#[test]
+fn test_solid_hulls() {
+    let mut data = Vec::new();
+    data.extend_from_slice(b"VPHY");
+    data.extend_from_slice(&0x100i16.to_le_bytes());
+    data.extend_from_slice(&0i16.to_le_bytes());
+    data.extend_from_slice(&[0; 20]);
+    // compact surface, ledge tree right after the surface header
+    data.extend_from_slice(&[0; 32]);
+    data.extend_from_slice(&48i32.to_le_bytes());
+    data.extend_from_slice(&[0; 12]);
+    // terminal ledge tree node pointing to the ledge right after it
+    data.extend_from_slice(&0i32.to_le_bytes());
+    data.extend_from_slice(&(LEDGE_TREE_NODE_SIZE as i32).to_le_bytes());
+    data.extend_from_slice(&[0; 20]);
+    // ledge with a single triangle, points after the triangle
+    data.extend_from_slice(&((LEDGE_SIZE + TRIANGLE_SIZE) as i32).to_le_bytes());
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&1i16.to_le_bytes());
+    data.extend_from_slice(&[0; 2]);
+    data.extend_from_slice(&[0; 4]);
+    for point in [0u32, 1, 2] {
+        data.extend_from_slice(&point.to_le_bytes());
+    }
+    for point in [
+        [0.0f32, 0.0, 0.0],
+        [0.0254, 0.0, 0.0],
+        [0.0, -0.0254, 0.0254],
+    ] {
+        for value in point {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&[0; 4]);
+    }
+
+    let hulls = PhysicsSolid { data }.hulls().unwrap();
+    assert_eq!(1, hulls.len());
+    assert_eq!(vec![[0, 1, 2]], hulls[0].triangles);
+    let last = hulls[0].vertices[2];
+    assert!((last.x - 0.0).abs() < 1e-4);
+    assert!((last.y - 1.0).abs() < 1e-4);
+    assert!((last.z - 1.0).abs() < 1e-4);
+}
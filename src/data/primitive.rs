@@ -0,0 +1,70 @@
+use binrw::BinRead;
+use std::mem::size_of;
+
+/// Explicit triangulation of a face, used to avoid t-junctions
+#[derive(Debug, Clone, BinRead)]
+pub struct Primitive {
+    #[br(pad_after = 1)]
+    pub ty: PrimitiveType,
+    pub first_index: u16,
+    pub index_count: u16,
+    pub first_vertex: u16,
+    /// Number of extra vertices used by the primitive, if 0 the indexes refer to the vertices of the face
+    pub vertex_count: u16,
+}
+
+static_assertions::const_assert_eq!(size_of::<Primitive>(), 10);
+
+#[test]
+fn test_primitive_bytes() {
+    super::test_read_bytes::<Primitive>();
+}
+
+#[repr(u8)]
+#[derive(BinRead, Debug, Copy, Clone, PartialEq, Eq)]
+#[br(repr = u8)]
+pub enum PrimitiveType {
+    TriangleList = 0,
+    TriangleStrip = 1,
+}
+
+impl Primitive {
+    /// Get the triangles of the primitive as indexes into the primitive's vertices
+    ///
+    /// Returns `None` if the primitive's indexes are out of bounds
+    pub fn triangles(&self, indices: &[u16]) -> Option<Vec<[u16; 3]>> {
+        let start = self.first_index as usize;
+        let indices = indices.get(start..start + self.index_count as usize)?;
+        Some(match self.ty {
+            PrimitiveType::TriangleList => indices
+                .chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                .collect(),
+            PrimitiveType::TriangleStrip => strip_to_triangles(indices).collect(),
+        })
+    }
+}
+
+/// Convert a triangle strip into a list of triangles, flipping every other triangle to keep the winding consistent
+pub(crate) fn strip_to_triangles<T: Copy + PartialEq>(
+    strip: &[T],
+) -> impl Iterator<Item = [T; 3]> + '_ {
+    strip
+        .windows(3)
+        .enumerate()
+        .map(|(i, window)| {
+            if i % 2 == 0 {
+                [window[0], window[1], window[2]]
+            } else {
+                [window[1], window[0], window[2]]
+            }
+        })
+        // degenerate triangles are used to join strips
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+}
+
+#[test]
+fn test_strip_to_triangles() {
+    let triangles: Vec<_> = strip_to_triangles(&[0, 1, 2, 3, 3, 4, 4, 5, 6]).collect();
+    assert_eq!(vec![[0, 1, 2], [2, 1, 3], [4, 5, 6]], triangles);
+}
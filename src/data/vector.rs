@@ -1,12 +1,14 @@
 use crate::error::EntityParseError;
 use binrw::BinRead;
+use bytemuck::{Pod, Zeroable};
 use cgmath::Vector3;
 use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::ops::{Add, Mul, Sub};
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, BinRead)]
+#[derive(Debug, Clone, Copy, BinRead, Pod, Zeroable)]
+#[repr(C)]
 pub struct Vector {
     pub x: f32,
     pub y: f32,
@@ -21,6 +23,22 @@ impl Vector {
     pub fn length_squared(&self) -> f32 {
         self.x.powf(2.0) + self.y.powf(2.0) + self.z.powf(2.0)
     }
+
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn dot(&self, other: &Vector) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Vector) -> Vector {
+        Vector {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
 }
 
 impl Add<Vector> for Vector {
@@ -110,3 +128,13 @@ impl From<Vector> for Vector3<f32> {
         Vector3::new(v.x, v.y, v.z)
     }
 }
+
+impl From<Vector3<f32>> for Vector {
+    fn from(v: Vector3<f32>) -> Self {
+        Vector {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::bspfile::LumpType;
 use crate::data::*;
 use std::num::{ParseFloatError, ParseIntError};
+use std::sync::Arc;
 use thiserror::Error;
 use zip::result::ZipError;
 
@@ -15,6 +16,8 @@ pub enum BspError {
     GameLumpOutOfBounds(GameLump),
     #[error("compressed game lump is malformed")]
     MalformedCompressedGameLump,
+    #[error("failed to read the game lump: {0}")]
+    GameLump(Arc<BspError>),
     #[error("Invalid lump size, lump size {lump_size} is not a multiple of the element size {element_size}")]
     InvalidLumpSize {
         lump: LumpType,
@@ -33,6 +36,8 @@ pub enum BspError {
     String(#[from] StringError),
     #[error("Malformed field found while parsing: {0:#}")]
     MalformedData(binrw::Error),
+    #[error("visibility data for cluster {cluster} at offset {offset} is out of bounds")]
+    VisDataOutOfBounds { cluster: usize, offset: i32 },
     #[error("bsp file is well-formed but contains invalid data")]
     Validation(#[from] ValidationError),
     #[error(transparent)]
@@ -93,7 +98,7 @@ pub struct UnsupportedLumpVersion {
     pub version: u16,
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum ValidationError {
     #[error(
     "A {source_} indexes into {target} but the index {index} is out of range of the size {size}"
@@ -108,13 +113,46 @@ pub enum ValidationError {
     NoRootNode,
     #[error("displacement face with {0} edges")]
     NonSquareDisplacement(i16),
+    #[error("displacement with unsupported power {0}")]
+    InvalidDisplacementPower(i32),
+    #[error("node {0} is reachable from more than one parent")]
+    NodeCycle(usize),
+    #[error("texture string offset {0} is inside a utf8 character")]
+    StringOffsetNotCharBoundary(i32),
+    #[error(transparent)]
+    Neighbour(InvalidNeighbourError),
+}
+
+/// Non-fatal problems encountered while reading a bsp file
+#[non_exhaustive]
+#[derive(Debug, Clone, Error)]
+pub enum BspWarning {
     #[error("No static prop lump found")]
     NoStaticPropLump,
+    #[error("Lump file for {lump:?} was made for map revision {lump_file_revision} but the map has revision {map_revision}")]
+    LumpFileRevisionMismatch {
+        lump: LumpType,
+        lump_file_revision: u32,
+        map_revision: u32,
+    },
+    #[error("Lump file for unknown lump id {0}")]
+    UnknownLumpFile(u32),
+    #[error("Failed to read the {lump:?} lump, it has been skipped: {error}")]
+    InvalidLump {
+        lump: LumpType,
+        error: Arc<BspError>,
+    },
+    #[error("Invalid element {element} in the {lump:?} lump: {error}")]
+    InvalidElement {
+        lump: LumpType,
+        element: usize,
+        error: ValidationError,
+    },
     #[error(transparent)]
-    Neighbour(InvalidNeighbourError),
+    Validation(ValidationError),
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum InvalidNeighbourError {
     #[error("Invalid neighbour span")]
     InvalidNeighbourSpan(u8),
@@ -0,0 +1,32 @@
+use super::Handle;
+use crate::data::*;
+use crate::BspResult;
+
+impl Handle<'_, CubeMap> {
+    /// Get the path of the cubemap texture in the packfile
+    ///
+    /// Cubemaps are packed as `materials/maps/<map>/c<x>_<y>_<z>.vtf`, or `.hdr.vtf` for the
+    /// hdr variant. Returns `None` if the map name can't be derived from the packfile
+    pub fn texture_path(&self, mode: LightingMode) -> Option<String> {
+        let map = self.bsp.pack.map_name()?;
+        let [x, y, z] = self.origin;
+        let extension = match mode {
+            LightingMode::Ldr => "vtf",
+            LightingMode::Hdr => "hdr.vtf",
+        };
+        Some(format!(
+            "materials/maps/{}/c{}_{}_{}.{}",
+            map, x, y, z, extension
+        ))
+    }
+
+    /// Load the cubemap texture from the packfile
+    ///
+    /// Returns `None` if the texture isn't packed into the bsp
+    pub fn texture(&self, mode: LightingMode) -> BspResult<Option<Vec<u8>>> {
+        match self.texture_path(mode) {
+            Some(path) => self.bsp.pack.get(&path),
+            None => Ok(None),
+        }
+    }
+}
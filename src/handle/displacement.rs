@@ -1,6 +1,7 @@
-use super::Handle;
+use super::{index_in, Handle};
 use crate::data::*;
 use arrayvec::ArrayVec;
+use std::cmp::Ordering;
 
 impl<'a> Handle<'a, DisplacementInfo> {
     pub fn edge_neighbours(&self) -> impl Iterator<Item = Handle<'a, DisplacementSubNeighbour>> {
@@ -24,19 +25,53 @@ impl<'a> Handle<'a, DisplacementInfo> {
             .flat_map(|i| self.bsp.displacement_vertex(i as usize))
     }
 
+    /// Get the multiblend data of the displacement, with one entry for each displacement vertex
+    ///
+    /// Returns `None` if the displacement doesn't use multiblend
+    pub fn multiblend(&self) -> Option<&'a [DisplacementMultiBlend]> {
+        if !self.has_multiblend() {
+            return None;
+        }
+        let index = index_in(&self.bsp.displacements, self.data)?;
+        let start = *self.bsp.displacement_multiblend_offsets.get(index)?;
+        self.bsp
+            .displacement_multiblend
+            .get(start..start + self.vertex_count() as usize)
+    }
+
+    /// Get the vertices of the displacement along with their multiblend data, if any
+    pub fn multiblend_vertices(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            Handle<'a, DisplacementVertex>,
+            Option<&'a DisplacementMultiBlend>,
+        ),
+    > {
+        let multiblend = self.multiblend().unwrap_or_default();
+        self.displacement_vertices()
+            .enumerate()
+            .map(move |(i, vertex)| (vertex, multiblend.get(i)))
+    }
+
     pub fn face(&self) -> Option<Handle<'a, Face>> {
         self.bsp.face(self.map_face as usize)
     }
 
     /// Get the positions of the corners of the displaced face
-    fn corner_positions(&self) -> [Vector; 4] {
-        let face = self.face().unwrap();
+    ///
+    /// Returns `None` if the face doesn't exist or isn't a quad
+    fn corner_positions(&self) -> Option<[Vector; 4]> {
+        let face = self.face()?;
+        if face.num_edges != 4 {
+            return None;
+        }
         let vertices: [_; 4] = face
-            .vertices()
+            .try_vertices()?
             .collect::<ArrayVec<_, 4>>()
             .as_ref()
             .try_into()
-            .unwrap();
+            .ok()?;
         let mut corner_positions: [Vector; 4] = vertices.map(|v| v.position);
 
         // find the corner closest to the start position of the displacement
@@ -45,17 +80,24 @@ impl<'a> Handle<'a, DisplacementInfo> {
             .copied()
             .map(|point| point - self.start_position)
             .enumerate()
-            .min_by(|(_a, a_pos), (_b, b_pos)| (a_pos).partial_cmp(b_pos).unwrap())
-            .map(|(i, _pos)| i)
-            .unwrap();
+            .min_by(|(_a, a_pos), (_b, b_pos)| {
+                (a_pos).partial_cmp(b_pos).unwrap_or(Ordering::Equal)
+            })
+            .map(|(i, _pos)| i)?;
 
         corner_positions.rotate_left(start_index);
-        corner_positions
+        Some(corner_positions)
     }
 
+    /// Get the vertices of the undisplaced face, empty if the face isn't a valid quad
     fn subdivided_face(&self) -> impl Iterator<Item = Vector> + 'a {
-        let steps = 2usize.pow(self.power as u32) + 1;
         let corner_positions = self.corner_positions();
+        let steps = if corner_positions.is_some() {
+            2usize.pow(self.power as u32) + 1
+        } else {
+            0
+        };
+        let corner_positions = corner_positions.unwrap_or([Vector::from([0.0; 3]); 4]);
 
         let step_scale = 1.0 / (steps as f32 - 1.0);
         let edge_intervals = [
@@ -91,15 +133,122 @@ impl<'a> Handle<'a, DisplacementInfo> {
             .flat_map(move |x| (0..steps).map(move |y| (x, y)))
             .flat_map(move |(x, y)| {
                 [
-                    vertices[index(x, y)],
-                    vertices[index(x + 1, y)],
-                    vertices[index(x, y + 1)],
-                    vertices[index(x, y + 1)],
-                    vertices[index(x + 1, y + 1)],
-                    vertices[index(x + 1, y)],
+                    index(x, y),
+                    index(x + 1, y),
+                    index(x, y + 1),
+                    index(x, y + 1),
+                    index(x + 1, y + 1),
+                    index(x + 1, y),
                 ]
+                .into_iter()
+                .map(|index| vertices.get(index).copied())
+                .collect::<Option<ArrayVec<_, 6>>>()
+                .unwrap_or_default()
             })
     }
+
+    /// Get the triangles of the displacement along with their tags
+    ///
+    /// The triangles are in the same order as the tags in the displacement triangle lump
+    pub fn triangles(&self) -> impl Iterator<Item = DisplacedTriangle> + 'a {
+        let vertices: Vec<_> = self.displaced_vertices().collect();
+        let width = 2usize.pow(self.power as u32) + 1;
+        let contents = BrushFlags::from_bits_truncate(self.contents as u32);
+        let start = self.displacement_triangle_tag_start.max(0) as usize;
+        let tags = self
+            .bsp
+            .displacement_triangles
+            .get(start..start + self.triangle_count() as usize)
+            .unwrap_or_default();
+
+        (0..(width - 1))
+            .flat_map(move |v| (0..(width - 1)).map(move |u| v * width + u))
+            .flat_map(move |index| {
+                // the split direction alternates between quads
+                if index % 2 == 1 {
+                    [
+                        [index, index + width, index + 1],
+                        [index + 1, index + width, index + width + 1],
+                    ]
+                } else {
+                    [
+                        [index, index + width, index + width + 1],
+                        [index, index + width + 1, index + 1],
+                    ]
+                }
+            })
+            .enumerate()
+            .filter_map(move |(i, triangle)| {
+                let [a, b, c] = triangle.map(|index| vertices.get(index).copied());
+                Some(DisplacedTriangle {
+                    vertices: [a?, b?, c?],
+                    tags: tags
+                        .get(i)
+                        .map(|triangle| triangle.tags)
+                        .unwrap_or_else(DisplacementTriangleFlags::empty),
+                    contents,
+                })
+            })
+    }
+
+    /// Get the number of lightmap luxels on the displacement
+    pub fn lightmap_luxel_count(&self) -> usize {
+        self.face()
+            .map(|face| {
+                // the stored size is one less than the number of luxels
+                let [width, height] = face
+                    .light_map_texture_size
+                    .map(|size| (size.max(-1) as i64 + 1) as usize);
+                width.saturating_mul(height)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get the alpha values for each lightmap luxel of the displacement
+    ///
+    /// Returns an empty slice if the map doesn't contain lightmap alphas
+    pub fn lightmap_alphas(&self) -> &'a [u8] {
+        let start = self.lightmap_alpha_start.max(0) as usize;
+        self.bsp
+            .displacement_lightmap_alphas
+            .get(start..start.saturating_add(self.lightmap_luxel_count()))
+            .unwrap_or_default()
+    }
+
+    /// Get the location of each lightmap luxel of the displacement
+    ///
+    /// The samples reference the triangles as returned by [`triangles`](Self::triangles)
+    pub fn lightmap_samples(&self) -> impl Iterator<Item = DisplacementLightmapSample> + 'a {
+        let mut data = self
+            .bsp
+            .displacement_lightmap_sample_positions
+            .get(self.lightmap_sample_position_start.max(0) as usize..)
+            .unwrap_or_default();
+        std::iter::from_fn(move || {
+            let (sample, used) = DisplacementLightmapSample::decode(data)?;
+            data = &data[used..];
+            Some(sample)
+        })
+        .take(self.lightmap_luxel_count())
+    }
+
+    /// Get the world position of each lightmap luxel of the displacement
+    pub fn lightmap_sample_positions(&self) -> impl Iterator<Item = Vector> + 'a {
+        let triangles: Vec<_> = self.triangles().collect();
+        self.lightmap_samples().filter_map(move |sample| {
+            let triangle = triangles.get(sample.triangle as usize)?;
+            Some(sample.position(triangle))
+        })
+    }
+
+    /// Get the collision data for the displacement
+    pub fn physics(&self) -> Option<&'a PhysicsSolid> {
+        let index = self.face()?.displacement_index()?;
+        self.bsp
+            .displacement_physics
+            .get(index as usize)
+            .filter(|solid| !solid.data.is_empty())
+    }
 }
 
 impl<'a> Handle<'a, DisplacementSubNeighbour> {
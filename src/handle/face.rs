@@ -1,53 +1,163 @@
-use super::Handle;
+use super::{index_in, Handle, NO_TEXTURE_INFO};
 use crate::data::*;
+use crate::Bsp;
+use std::ops::Range;
 
 impl<'a> Handle<'a, Face> {
     /// Get the texture of the face
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texture index is out of range, see [`try_texture`](Self::try_texture)
     pub fn texture(&self) -> Handle<'a, TextureInfo> {
+        self.try_texture().expect("face texture out of range")
+    }
+
+    /// Get the texture of the face, `None` if the texture index is out of range
+    ///
+    /// Faces with a negative texture index get an empty texture without flags or name
+    pub fn try_texture(&self) -> Option<Handle<'a, TextureInfo>> {
+        let index = match usize::try_from(self.texture_info) {
+            Ok(index) => index,
+            Err(_) => return Some(Handle::new(self.bsp, &NO_TEXTURE_INFO)),
+        };
         self.bsp
             .textures_info
-            .get(self.texture_info as usize)
+            .get(index)
             .map(|texture_info| Handle {
                 bsp: self.bsp,
                 data: texture_info,
             })
-            .unwrap()
+    }
+
+    /// Get the original face this face was split from during compilation
+    pub fn original(&self) -> Option<Handle<'a, Face>> {
+        let index = usize::try_from(self.original_face).ok()?;
+        self.bsp
+            .original_faces
+            .get(index)
+            .map(|face| Handle::new(self.bsp, face))
+    }
+
+    /// Get the id of the face in the hammer map the bsp was compiled from
+    ///
+    /// Returns `None` for original faces or if the bsp doesn't contain face ids
+    pub fn hammer_face_id(&self) -> Option<u16> {
+        self.bsp
+            .face_ids
+            .get(self.index()?)
+            .map(|face_id| face_id.hammer_face_id)
+    }
+
+    /// Get the name of the macro texture of the face
+    ///
+    /// Returns `None` if the face has no macro texture
+    pub fn macro_texture(&self) -> Option<&'a str> {
+        let info = self.bsp.face_macro_textures.get(self.index()?)?;
+        if info.texture_string_table_index == u16::MAX {
+            return None;
+        }
+        self.bsp
+            .texture_string(info.texture_string_table_index as usize)
+    }
+
+    /// Find the index of this face in the `bsp`'s `faces` array
+    fn index(&self) -> Option<usize> {
+        index_in(&self.bsp.faces, self.data)
     }
 
     /// Get all vertices making up the face
+    ///
+    /// # Panics
+    ///
+    /// Panics if the face references edges or vertices out of range, see
+    /// [`try_vertices`](Self::try_vertices)
     pub fn vertices(&self) -> impl Iterator<Item = &'a Vertex> + 'a {
         let bsp = self.bsp;
-        self.vertex_indexes()
-            .map(move |vert_index| bsp.vertices.get(vert_index as usize).unwrap())
+        self.vertex_indexes().map(move |vert_index| {
+            bsp.vertices
+                .get(vert_index as usize)
+                .expect("face vertex out of range")
+        })
+    }
+
+    /// Get all vertices making up the face, `None` if the face references edges or vertices out of range
+    pub fn try_vertices(&self) -> Option<impl Iterator<Item = &'a Vertex> + 'a> {
+        let bsp = self.bsp;
+        let vertices = self
+            .try_vertex_indexes()?
+            .map(|vert_index| bsp.vertices.get(vert_index as usize))
+            .collect::<Option<Vec<_>>>()?;
+        Some(vertices.into_iter())
     }
 
     /// Get the vertex indexes of all vertices making up the face
     ///
     /// The indexes index into the `vertices` field of the bsp file
+    ///
+    /// # Panics
+    ///
+    /// Panics if the face references edges out of range, see
+    /// [`try_vertex_indexes`](Self::try_vertex_indexes)
     pub fn vertex_indexes(&self) -> impl Iterator<Item = u16> + 'a {
         let bsp = self.bsp;
-        (self.data.first_edge..(self.data.first_edge + self.data.num_edges as i32))
-            .map(move |surface_edge| bsp.surface_edges.get(surface_edge as usize).unwrap())
-            .map(move |surface_edge| {
-                bsp.edges
-                    .get(surface_edge.edge_index() as usize)
-                    .map(|edge| (edge, surface_edge.direction()))
-                    .unwrap()
-            })
-            .map(|(edge, direction)| match direction {
-                EdgeDirection::FirstToLast => edge.start_index,
-                EdgeDirection::LastToFirst => edge.end_index,
-            })
+        self.surface_edge_range().map(move |surface_edge| {
+            vertex_index(bsp, surface_edge).expect("face edge out of range")
+        })
     }
 
+    /// Get the vertex indexes of all vertices making up the face, `None` if the face references edges out of range
+    ///
+    /// The indexes index into the `vertices` field of the bsp file
+    pub fn try_vertex_indexes(&self) -> Option<impl Iterator<Item = u16> + 'a> {
+        let bsp = self.bsp;
+        let indexes = self
+            .surface_edge_range()
+            .map(|surface_edge| vertex_index(bsp, surface_edge))
+            .collect::<Option<Vec<_>>>()?;
+        Some(indexes.into_iter())
+    }
+
+    fn surface_edge_range(&self) -> Range<i64> {
+        let start = self.first_edge as i64;
+        start..(start + self.num_edges as i64)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the first edge of the face is out of range, see
+    /// [`try_edge_direction`](Self::try_edge_direction)
     pub fn edge_direction(&self) -> EdgeDirection {
-        self.bsp.surface_edges[self.first_edge as usize].direction()
+        self.try_edge_direction().expect("face edge out of range")
+    }
+
+    /// Get the direction of the first edge, `None` if the edge is out of range
+    ///
+    /// Faces without edges are always [`FirstToLast`](EdgeDirection::FirstToLast)
+    pub fn try_edge_direction(&self) -> Option<EdgeDirection> {
+        if self.num_edges <= 0 {
+            return Some(EdgeDirection::FirstToLast);
+        }
+        let index = usize::try_from(self.first_edge).ok()?;
+        self.bsp
+            .surface_edges
+            .get(index)
+            .map(|surface_edge| surface_edge.direction())
     }
 
     /// Check if the face is flagged as visible
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texture index is out of range, see [`try_is_visible`](Self::try_is_visible)
     pub fn is_visible(&self) -> bool {
-        let texture = self.texture();
-        !texture.flags.intersects(
+        self.try_is_visible().expect("face texture out of range")
+    }
+
+    /// Check if the face is flagged as visible, `None` if the texture index is out of range
+    pub fn try_is_visible(&self) -> Option<bool> {
+        let texture = self.try_texture()?;
+        Some(!texture.flags.intersects(
             TextureFlags::LIGHT
                 | TextureFlags::SKY2D
                 | TextureFlags::SKY
@@ -57,26 +167,195 @@ impl<'a> Handle<'a, Face> {
                 | TextureFlags::SKIP
                 | TextureFlags::NODRAW
                 | TextureFlags::HITBOX,
-        )
+        ))
+    }
+
+    /// Get the primitives used to triangulate the face
+    pub fn primitives(&self) -> impl Iterator<Item = &'a Primitive> {
+        self.bsp
+            .primitives
+            .get(self.primitive_range())
+            .unwrap_or_default()
+            .iter()
     }
 
     /// Triangulate the face
     ///
-    /// Triangulation only works for faces that can be turned into a triangle fan trivially
+    /// If the face has primitives, the triangulation generated by the compiler is used,
+    /// otherwise the face is triangulated using ear clipping.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the face references edges or vertices out of range, see
+    /// [`try_triangulate`](Self::try_triangulate)
     pub fn triangulate(&self) -> impl Iterator<Item = [Vector; 3]> + 'a {
-        let mut vertices = self.vertices();
+        let vertices: Vec<Vector> = self.vertices().map(|vertex| vertex.position).collect();
+        self.triangulate_vertices(vertices)
+    }
 
-        let a = vertices.next().expect("face with <3 points");
-        let mut b = vertices.next().expect("face with <3 points");
+    /// Triangulate the face, `None` if the face references edges or vertices out of range
+    ///
+    /// See [`triangulate`](Self::triangulate)
+    pub fn try_triangulate(&self) -> Option<impl Iterator<Item = [Vector; 3]> + 'a> {
+        let vertices: Vec<Vector> = self.try_vertices()?.map(|vertex| vertex.position).collect();
+        Some(self.triangulate_vertices(vertices))
+    }
 
-        vertices.map(move |c| {
-            let points = [a.position, b.position, c.position];
-            b = c;
-            points
-        })
+    fn triangulate_vertices(
+        &self,
+        vertices: Vec<Vector>,
+    ) -> impl Iterator<Item = [Vector; 3]> + 'a {
+        let triangles = if self.primitive_range().is_empty() {
+            None
+        } else {
+            self.primitive_triangles(&vertices)
+        };
+        triangles.unwrap_or_else(|| ear_clip(&vertices)).into_iter()
+    }
+
+    fn primitive_triangles(&self, face_vertices: &[Vector]) -> Option<Vec<[Vector; 3]>> {
+        let bsp = self.bsp;
+        let mut triangles = Vec::new();
+        for primitive in self.primitives() {
+            let vertex = |index: u16| -> Option<Vector> {
+                if primitive.vertex_count == 0 {
+                    face_vertices.get(index as usize).copied()
+                } else if index < primitive.vertex_count {
+                    bsp.primitive_vertices
+                        .get(primitive.first_vertex as usize + index as usize)
+                        .map(|vertex| vertex.position)
+                } else {
+                    None
+                }
+            };
+            for triangle in primitive.triangles(&bsp.primitive_indices)? {
+                let [a, b, c] = triangle.map(vertex);
+                triangles.push([a?, b?, c?]);
+            }
+        }
+        Some(triangles)
     }
 
     pub fn displacement(&self) -> Option<Handle<'a, DisplacementInfo>> {
         self.bsp.displacement(self.displacement_info as usize)
     }
 }
+
+/// Get the index of the vertex the surface edge starts at
+fn vertex_index(bsp: &Bsp, surface_edge: i64) -> Option<u16> {
+    let surface_edge = bsp.surface_edges.get(usize::try_from(surface_edge).ok()?)?;
+    let edge = bsp.edges.get(surface_edge.edge_index() as usize)?;
+    Some(match surface_edge.direction() {
+        EdgeDirection::FirstToLast => edge.start_index,
+        EdgeDirection::LastToFirst => edge.end_index,
+    })
+}
+
+/// Triangulate a planar polygon using ear clipping
+///
+/// Polygons with less than 3 points result in no triangles
+fn ear_clip(vertices: &[Vector]) -> Vec<[Vector; 3]> {
+    if vertices.len() < 3 {
+        return Vec::new();
+    }
+
+    // newell's method, gives the normal matching the winding of the polygon
+    let normal = vertices.iter().zip(vertices.iter().cycle().skip(1)).fold(
+        Vector::from([0.0; 3]),
+        |normal, (a, b)| {
+            normal
+                + Vector {
+                    x: (a.y - b.y) * (a.z + b.z),
+                    y: (a.z - b.z) * (a.x + b.x),
+                    z: (a.x - b.x) * (a.y + b.y),
+                }
+        },
+    );
+    let is_convex =
+        |a: Vector, b: Vector, c: Vector| (b - a).cross(&(c - b)).dot(&normal) > f32::EPSILON;
+    let is_inside = |point: Vector, [a, b, c]: [Vector; 3]| {
+        (b - a).cross(&(point - a)).dot(&normal) >= 0.0
+            && (c - b).cross(&(point - b)).dot(&normal) >= 0.0
+            && (a - c).cross(&(point - c)).dot(&normal) >= 0.0
+    };
+
+    let mut remaining: Vec<usize> = (0..vertices.len()).collect();
+    let mut triangles = Vec::with_capacity(vertices.len() - 2);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let [a, b, c] = [
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            ];
+            let triangle = [vertices[a], vertices[b], vertices[c]];
+            is_convex(triangle[0], triangle[1], triangle[2])
+                && !remaining
+                    .iter()
+                    .filter(|index| ![a, b, c].contains(index))
+                    .any(|index| is_inside(vertices[*index], triangle))
+        });
+
+        match ear {
+            Some(i) => {
+                let [a, b, c] = [
+                    remaining[(i + count - 1) % count],
+                    remaining[i],
+                    remaining[(i + 1) % count],
+                ];
+                triangles.push([vertices[a], vertices[b], vertices[c]]);
+                remaining.remove(i);
+            }
+            None => {
+                // degenerate polygon, fall back to a fan for the remaining points
+                let first = remaining[0];
+                for pair in remaining[1..].windows(2) {
+                    triangles.push([vertices[first], vertices[pair[0]], vertices[pair[1]]]);
+                }
+                return triangles;
+            }
+        }
+    }
+
+    triangles.push([
+        vertices[remaining[0]],
+        vertices[remaining[1]],
+        vertices[remaining[2]],
+    ]);
+    triangles
+}
+
+#[test]
+fn test_ear_clip() {
+    let points =
+        |points: &[[f32; 3]]| -> Vec<Vector> { points.iter().copied().map(Vector::from).collect() };
+
+    assert!(ear_clip(&points(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]])).is_empty());
+
+    let square = points(&[
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ]);
+    assert_eq!(2, ear_clip(&square).len());
+
+    // concave "L" shape, a fan from the first point would go outside the polygon
+    let concave = points(&[
+        [0.0, 0.0, 0.0],
+        [2.0, 0.0, 0.0],
+        [2.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 2.0, 0.0],
+        [0.0, 2.0, 0.0],
+    ]);
+    let triangles = ear_clip(&concave);
+    assert_eq!(4, triangles.len());
+    let area: f32 = triangles
+        .iter()
+        .map(|[a, b, c]| (*b - *a).cross(&(*c - *a)).length() / 2.0)
+        .sum();
+    assert!((area - 3.0).abs() < 1e-6);
+}
@@ -1,8 +1,110 @@
 use super::Handle;
 use crate::data::*;
+#[cfg(feature = "mdl")]
+use crate::{
+    mdl::{Model, ModelVertex, PropMesh, PropModel},
+    BspResult,
+};
+#[cfg(feature = "mdl")]
+use cgmath::Vector3;
 
-impl Handle<'_, StaticPropLump> {
-    pub fn model(&self) -> &str {
-        self.bsp.static_props.dict.name[self.prop_type as usize].as_str()
+impl<'a> Handle<'a, StaticPropLump> {
+    /// # Panics
+    ///
+    /// Panics if the model index is out of range, see [`try_model`](Self::try_model)
+    pub fn model(&self) -> &'a str {
+        self.try_model().expect("static prop model out of range")
+    }
+
+    /// Get the name of the prop's model, `None` if the model index is out of range
+    pub fn try_model(&self) -> Option<&'a str> {
+        self.bsp
+            .static_props
+            .dict
+            .name
+            .get(self.prop_type as usize)
+            .map(|name| name.as_str())
+    }
+
+    /// Get all leaves the prop is in
+    pub fn leaves(&self) -> impl Iterator<Item = Handle<'a, Leaf>> {
+        let bsp = self.bsp;
+        bsp.static_props
+            .leaf
+            .leaves
+            .get(self.leaf_range())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(move |leaf| bsp.leaf(*leaf as usize))
+    }
+}
+
+#[cfg(feature = "mdl")]
+impl<'a> Handle<'a, StaticPropLump> {
+    /// Load the model of the prop from the bsp's packfile
+    ///
+    /// Returns `None` if the model isn't packed into the bsp or the model index is out of range
+    pub fn load_model(&self) -> BspResult<Option<Model>> {
+        match self.try_model() {
+            Some(name) => Model::from_packfile(&self.bsp.pack, name),
+            None => Ok(None),
+        }
+    }
+
+    /// Load the model of the prop from the bsp's packfile and place it in the world
+    ///
+    /// The vertices are transformed by the rotation and origin of the prop and the materials
+    /// are resolved using the prop's skin.
+    ///
+    /// Returns `None` if the model isn't packed into the bsp or the model index is out of range
+    pub fn load_mesh(&self) -> BspResult<Option<PropModel>> {
+        let model = match self.load_model()? {
+            Some(model) => model,
+            None => return Ok(None),
+        };
+
+        let rotation = self.rotation();
+        let origin = self.origin;
+        let transform = |vector: Vector| -> Vector { (rotation * Vector3::from(vector)).into() };
+        let vertices = model
+            .vertices
+            .iter()
+            .map(|vertex| ModelVertex {
+                position: transform(vertex.position) + origin,
+                normal: transform(vertex.normal),
+                uv: vertex.uv,
+            })
+            .collect();
+
+        let meshes = model
+            .meshes
+            .iter()
+            .map(|mesh| PropMesh {
+                material: self
+                    .resolve_material(&model, model.texture(mesh, self.skin.max(0) as usize)),
+                indices: mesh.indices.clone(),
+            })
+            .collect();
+
+        Ok(Some(PropModel { vertices, meshes }))
+    }
+
+    /// Find the texture directory containing the material, preferring materials packed in the bsp
+    fn resolve_material(&self, model: &Model, texture: Option<&str>) -> String {
+        let texture = texture.unwrap_or_default();
+        let candidates = model
+            .texture_dirs
+            .iter()
+            .map(|dir| format!("{}{}", dir, texture).to_ascii_lowercase());
+        candidates
+            .clone()
+            .find(|candidate| {
+                self.bsp
+                    .pack
+                    .contains(&format!("materials/{}.vmt", candidate))
+                    .unwrap_or_default()
+            })
+            .or_else(|| candidates.clone().next())
+            .unwrap_or_else(|| texture.to_ascii_lowercase())
     }
 }
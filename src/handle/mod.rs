@@ -1,10 +1,14 @@
+mod cubemap;
 mod displacement;
 mod face;
 mod game;
+mod occlusion;
+mod overlay;
 
 use crate::data::*;
 use crate::Bsp;
 use std::fmt::{Debug, Formatter};
+use std::mem::size_of;
 use std::ops::Deref;
 
 /// A handle represents a data structure in the bsp file and the bsp file containing it.
@@ -50,14 +54,50 @@ impl<'a, T> Handle<'a, T> {
     }
 }
 
+/// Texture info used for references with a negative index, which mark a missing texture
+static NO_TEXTURE_INFO: TextureInfo = TextureInfo {
+    texture_scale: [0.0; 4],
+    texture_transform: [0.0; 4],
+    light_map_scale: [0.0; 4],
+    light_map_transform: [0.0; 4],
+    flags: TextureFlags::empty(),
+    texture_data_index: -1,
+};
+
+/// Texture data used for references with a negative index, which mark missing texture data
+static NO_TEXTURE_DATA: TextureData = TextureData {
+    reflectivity: Vector {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    },
+    name_string_table_id: -1,
+    width: 0,
+    height: 0,
+    view_width: 0,
+    view_height: 0,
+};
+
+/// Find the index of an item in a list from the bsp
+///
+/// Handles don't store their index, but it can be derived from the address of the data.
+/// Returns `None` if the item isn't part of the list.
+fn index_in<T>(list: &[T], item: &T) -> Option<usize> {
+    let offset = (item as *const T as usize).checked_sub(list.as_ptr() as usize)?;
+    let index = offset.checked_div(size_of::<T>())?;
+    (index < list.len()).then_some(index)
+}
+
 impl<'a> Handle<'a, Model> {
     /// Get all faces that make up the model
     pub fn faces(&self) -> impl Iterator<Item = Handle<'a, Face>> {
-        let start = self.first_face as usize;
-        let end = start + self.face_count as usize;
+        let start = self.first_face.max(0) as usize;
+        let end = start + self.face_count.max(0) as usize;
         let bsp = self.bsp;
 
-        bsp.faces[start..end]
+        bsp.faces
+            .get(start..end)
+            .unwrap_or_default()
             .iter()
             .map(move |face| Handle::new(bsp, face))
     }
@@ -65,20 +105,29 @@ impl<'a> Handle<'a, Model> {
 
 impl<'a> Handle<'a, TextureInfo> {
     /// Get the texture data references by the texture
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texture data index is out of range, see
+    /// [`try_texture_data`](Self::try_texture_data)
     pub fn texture(&self) -> Handle<'a, TextureData> {
-        let texture = self
-            .bsp
-            .textures_data
-            .get(self.data.texture_data_index as usize)
-            .unwrap();
-        Handle::new(self.bsp, texture)
+        self.texture_data()
     }
 }
 
 impl Handle<'_, Node> {
     /// Get the plane splitting this node
+    ///
+    /// # Panics
+    ///
+    /// Panics if the plane index is out of range, see [`try_plane`](Self::try_plane)
     pub fn plane(&self) -> Handle<'_, Plane> {
-        self.bsp.plane(self.plane_index as _).unwrap()
+        self.try_plane().expect("node plane out of range")
+    }
+
+    /// Get the plane splitting this node, `None` if the plane index is out of range
+    pub fn try_plane(&self) -> Option<Handle<'_, Plane>> {
+        self.bsp.plane(self.plane_index.try_into().ok()?)
     }
 
     pub fn children(&self) -> [Option<NodeOrLeaf<'_>>; 2] {
@@ -100,6 +149,8 @@ fn node_leaf(bsp: &Bsp, idx: i32) -> Option<NodeOrLeaf<'_>> {
 
 impl<'a> Handle<'a, Leaf> {
     /// Get all other leaves visible from this one
+    ///
+    /// Returns `None` if the leaf isn't in a cluster or the visibility data is malformed
     pub fn visible_set(&self) -> Option<impl Iterator<Item = Handle<'a, Leaf>>> {
         let cluster = self.cluster;
         let bsp = self.bsp;
@@ -107,14 +158,17 @@ impl<'a> Handle<'a, Leaf> {
         if cluster < 0 {
             None
         } else {
-            let visible_clusters = bsp.vis_data.visible_clusters(cluster);
+            let visible_clusters = bsp.vis_data.visible_clusters(cluster).ok()?;
             Some(
                 bsp.leaves
                     .iter()
+                    // cluster 0 is valid, only leaves with a negative cluster are outside the world
                     .filter(move |leaf| {
                         if leaf.cluster == cluster {
                             true
-                        } else if leaf.cluster > 0 {
+                        } else if leaf.cluster >= 0
+                            && (leaf.cluster as u64) < visible_clusters.len()
+                        {
                             visible_clusters[leaf.cluster as u64]
                         } else {
                             false
@@ -130,17 +184,56 @@ impl<'a> Handle<'a, Leaf> {
         let start = self.first_leaf_face as usize;
         let end = start + self.leaf_face_count as usize;
         let bsp = self.bsp;
-        bsp.leaf_faces[start..end]
+        bsp.leaf_faces
+            .get(start..end)
+            .unwrap_or_default()
             .iter()
             .filter_map(move |leaf_face| bsp.face(leaf_face.face as usize))
     }
 
+    /// Find the index of this leaf in the `bsp`'s `leaves` array
+    fn index(&self) -> Option<usize> {
+        index_in(&self.bsp.leaves, self.data)
+    }
+
+    /// Get all static props in this leaf
+    pub fn static_props(&self) -> impl Iterator<Item = Handle<'a, StaticPropLump>> {
+        let bsp = self.bsp;
+        let props = self
+            .index()
+            .map(|index| bsp.static_props.leaf_index.props_in_leaf(index))
+            .unwrap_or_default();
+        props
+            .iter()
+            .filter_map(move |prop| bsp.static_props.props.props.get(*prop))
+            .map(move |prop| Handle::new(bsp, prop))
+    }
+
+    /// Get the water in this leaf, if any
+    pub fn water_data(&self) -> Option<Handle<'a, LeafWaterData>> {
+        let index = usize::try_from(self.leaf_watter_data_id).ok()?;
+        self.bsp
+            .leaf_water_data
+            .get(index)
+            .map(|water| Handle::new(self.bsp, water))
+    }
+
+    /// Get the minimum distance from this leaf to any water
+    pub fn minimum_distance_to_water(&self) -> Option<u16> {
+        self.bsp
+            .leaf_minimum_distance_to_water
+            .get(self.index()?)
+            .copied()
+    }
+
     /// Get all faces in this leaf, and the index they are at in `bsp`'s `faces` array
     pub fn faces_enumerate(&self) -> impl Iterator<Item = (usize, Handle<'a, Face>)> {
         let start = self.first_leaf_face as usize;
         let end = start + self.leaf_face_count as usize;
         let bsp = self.bsp;
-        bsp.leaf_faces[start..end]
+        bsp.leaf_faces
+            .get(start..end)
+            .unwrap_or_default()
             .iter()
             .filter_map(move |leaf_face| {
                 bsp.face(leaf_face.face as usize)
@@ -149,26 +242,78 @@ impl<'a> Handle<'a, Leaf> {
     }
 }
 
+impl<'a> Handle<'a, LeafWaterData> {
+    /// Get the texture of the water surface
+    pub fn texture(&self) -> Option<Handle<'a, TextureInfo>> {
+        let index = usize::try_from(self.surface_texture_info).ok()?;
+        self.bsp
+            .textures_info
+            .get(index)
+            .map(|texture| Handle::new(self.bsp, texture))
+    }
+}
+
 impl<'a> Handle<'a, TextureInfo> {
+    /// # Panics
+    ///
+    /// Panics if the texture data index is out of range, see
+    /// [`try_texture_data`](Self::try_texture_data)
     pub fn texture_data(&self) -> Handle<'a, TextureData> {
-        Handle::new(
-            self.bsp,
-            &self.bsp.textures_data[self.data.texture_data_index as usize],
-        )
+        self.try_texture_data().expect("texture data out of range")
+    }
+
+    /// Get the texture data references by the texture, `None` if the texture data index is out of range
+    ///
+    /// A negative index gives empty texture data without a name
+    pub fn try_texture_data(&self) -> Option<Handle<'a, TextureData>> {
+        let index = match usize::try_from(self.data.texture_data_index) {
+            Ok(index) => index,
+            Err(_) => return Some(Handle::new(self.bsp, &NO_TEXTURE_DATA)),
+        };
+        self.bsp
+            .textures_data
+            .get(index)
+            .map(|texture| Handle::new(self.bsp, texture))
     }
+
+    /// # Panics
+    ///
+    /// Panics if the texture references data out of range, see [`try_name`](Self::try_name)
     pub fn name(&self) -> &'a str {
         self.texture_data().name()
     }
+
+    /// Get the name of the texture, `None` if the texture references data out of range
+    pub fn try_name(&self) -> Option<&'a str> {
+        self.try_texture_data()?.try_name()
+    }
 }
 
 impl<'a> Handle<'a, TextureData> {
+    /// # Panics
+    ///
+    /// Panics if the name references string data out of range, see [`try_name`](Self::try_name)
     pub fn name(&self) -> &'a str {
-        let start = self.bsp.texture_string_tables[self.name_string_table_id as usize] as usize;
-        let part = &self.bsp.texture_string_data[start..];
-        if let Some((s, _)) = part.split_once('\0') {
-            s
-        } else {
-            part
+        self.try_name().expect("texture name out of range")
+    }
+
+    /// Get the name of the texture, `None` if the name references string data out of range
+    ///
+    /// Texture data with a negative name index has an empty name
+    pub fn try_name(&self) -> Option<&'a str> {
+        match usize::try_from(self.name_string_table_id) {
+            Ok(index) => self.bsp.texture_string(index),
+            Err(_) => Some(""),
         }
     }
 }
+
+#[test]
+fn test_handle_index() {
+    let list = [1u32, 2, 3];
+    assert_eq!(Some(0), index_in(&list, &list[0]));
+    assert_eq!(Some(2), index_in(&list, &list[2]));
+    assert_eq!(None, index_in(&list[..2], &list[2]));
+    assert_eq!(None, index_in(&list[1..], &list[0]));
+    assert_eq!(None, index_in(&list, &2));
+}
@@ -0,0 +1,83 @@
+use super::Handle;
+use crate::data::*;
+
+impl<'a> Handle<'a, Occluder> {
+    /// Get the polygons making up the occluder
+    pub fn polygons(&self) -> impl Iterator<Item = Handle<'a, OccluderPolygon>> {
+        let bsp = self.bsp;
+        let start = self.first_polygon.max(0) as usize;
+        let end = start + self.polygon_count.max(0) as usize;
+        bsp.occlusion
+            .polygons
+            .get(start..end)
+            .unwrap_or_default()
+            .iter()
+            .map(move |polygon| Handle::new(bsp, polygon))
+    }
+
+    /// Get the total surface area of the occluder's polygons, not to be confused with the map `area` it is in
+    pub fn surface_area(&self) -> f32 {
+        self.polygons().map(|polygon| polygon.surface_area()).sum()
+    }
+}
+
+impl<'a> Handle<'a, OccluderPolygon> {
+    /// Get the plane of the polygon
+    pub fn plane(&self) -> Option<Handle<'a, Plane>> {
+        self.bsp.plane(self.data.plane as usize)
+    }
+
+    /// Get the vertices making up the polygon
+    pub fn vertices(&self) -> impl Iterator<Item = Vector> + 'a {
+        let bsp = self.bsp;
+        let start = self.first_vertex_index.max(0) as usize;
+        let end = start + self.vertex_count.max(0) as usize;
+        bsp.occlusion
+            .vertex_indices
+            .get(start..end)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(move |index| bsp.vertices.get(*index as usize))
+            .map(|vertex| vertex.position)
+    }
+
+    /// Get the surface area of the polygon
+    pub fn surface_area(&self) -> f32 {
+        let vertices: Vec<_> = self.vertices().collect();
+        let first = match vertices.first() {
+            Some(first) => *first,
+            None => return 0.0,
+        };
+        let doubled_area = vertices
+            .windows(2)
+            .skip(1)
+            .map(|pair| (pair[0] - first).cross(&(pair[1] - first)))
+            .fold(Vector::from([0.0; 3]), |sum, cross| sum + cross);
+        doubled_area.length() / 2.0
+    }
+
+    /// Check if a line segment passes through the polygon
+    pub fn intersects(&self, start: Vector, end: Vector) -> bool {
+        let vertices: Vec<_> = self.vertices().collect();
+        if vertices.len() < 3 {
+            return false;
+        }
+        let normal = (vertices[1] - vertices[0]).cross(&(vertices[2] - vertices[0]));
+
+        let start_distance = (start - vertices[0]).dot(&normal);
+        let end_distance = (end - vertices[0]).dot(&normal);
+        if start_distance.signum() == end_distance.signum() || start_distance == end_distance {
+            return false;
+        }
+
+        let t = start_distance / (start_distance - end_distance);
+        let point = start + (end - start) * t;
+
+        // occluder polygons are convex, so the point is inside if it's on the same side of every edge
+        let mut sides = vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .map(|(a, b)| (*b - *a).cross(&(point - *a)).dot(&normal));
+        sides.clone().all(|side| side >= 0.0) || sides.all(|side| side <= 0.0)
+    }
+}
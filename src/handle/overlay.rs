@@ -0,0 +1,166 @@
+use super::Handle;
+use crate::data::*;
+
+impl<'a, const FACE_COUNT: usize> Handle<'a, GenericOverlay<FACE_COUNT>> {
+    /// Get the texture of the overlay
+    pub fn texture(&self) -> Option<Handle<'a, TextureInfo>> {
+        self.bsp
+            .textures_info
+            .get(self.texture_info as usize)
+            .map(|texture_info| Handle::new(self.bsp, texture_info))
+    }
+
+    /// Get all faces the overlay is projected on
+    pub fn faces(&self) -> impl Iterator<Item = Handle<'a, Face>> + 'a {
+        let bsp = self.bsp;
+        self.data
+            .faces()
+            .iter()
+            .filter_map(move |face| bsp.face(*face as usize))
+    }
+
+    /// Project the overlay onto its faces, clipping it to the bounds of each face
+    pub fn polygons(&self) -> Vec<OverlayPolygon> {
+        self.data
+            .faces()
+            .iter()
+            .filter_map(|face| self.project(*face as usize))
+            .collect()
+    }
+
+    fn project(&self, face_index: usize) -> Option<OverlayPolygon> {
+        let face = self.bsp.face(face_index)?;
+        let plane = self.bsp.plane(face.plane_num as usize)?;
+        let [u_axis, v_axis, normal] = self.basis();
+        let origin = self.origin;
+
+        let denominator = plane.normal.dot(&normal);
+        if denominator.abs() < 1e-6 {
+            return None;
+        }
+
+        let face_points: Vec<[f32; 2]> = face
+            .try_vertices()?
+            .map(|vertex| {
+                let offset = vertex.position - origin;
+                [offset.dot(&u_axis), offset.dot(&v_axis)]
+            })
+            .collect();
+        let corners = self.corners_2d();
+        let clipped = clip_polygon(&face_points, &corners);
+        if clipped.len() < 3 {
+            return None;
+        }
+
+        let texture_coordinates = self.texture_coordinates();
+        let vertices = clipped
+            .into_iter()
+            .map(|point| {
+                let on_overlay = origin + u_axis * point[0] + v_axis * point[1];
+                let distance = (plane.dist - plane.normal.dot(&on_overlay)) / denominator;
+                OverlayVertex {
+                    position: on_overlay + normal * distance,
+                    uv: interpolate_quad(&corners, &texture_coordinates, point),
+                }
+            })
+            .collect();
+
+        Some(OverlayPolygon {
+            face: face_index,
+            vertices,
+        })
+    }
+}
+
+fn cross_2d(a: [f32; 2], b: [f32; 2], point: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (point[1] - a[1]) - (b[1] - a[1]) * (point[0] - a[0])
+}
+
+fn signed_area(polygon: &[[f32; 2]]) -> f32 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Clip a polygon by a convex polygon using Sutherland-Hodgman
+fn clip_polygon(subject: &[[f32; 2]], clip: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let orientation = signed_area(clip).signum();
+    let mut output = subject.to_vec();
+
+    for (&a, &b) in clip.iter().zip(clip.iter().cycle().skip(1)) {
+        let input = std::mem::take(&mut output);
+        let inside = |point: [f32; 2]| cross_2d(a, b, point) * orientation >= 0.0;
+
+        for (&current, &next) in input.iter().zip(input.iter().cycle().skip(1)) {
+            let current_inside = inside(current);
+            let next_inside = inside(next);
+            if current_inside {
+                output.push(current);
+            }
+            if current_inside != next_inside {
+                let current_distance = cross_2d(a, b, current);
+                let next_distance = cross_2d(a, b, next);
+                let t = current_distance / (current_distance - next_distance);
+                output.push([
+                    current[0] + (next[0] - current[0]) * t,
+                    current[1] + (next[1] - current[1]) * t,
+                ]);
+            }
+        }
+    }
+
+    output
+}
+
+/// Interpolate the values at the corners of a quad for a point inside the quad
+fn interpolate_quad(corners: &[[f32; 2]; 4], values: &[[f32; 2]; 4], point: [f32; 2]) -> [f32; 2] {
+    // split the quad into two triangles and use the barycentric coordinates in the one containing the point
+    let triangles = [[0, 1, 2], [0, 2, 3]];
+    let weights = triangles.map(|[a, b, c]| {
+        let area = cross_2d(corners[a], corners[b], corners[c]);
+        if area == 0.0 {
+            return None;
+        }
+        let wa = cross_2d(corners[b], corners[c], point) / area;
+        let wb = cross_2d(corners[c], corners[a], point) / area;
+        Some(([a, b, c], [wa, wb, 1.0 - wa - wb]))
+    });
+    let ([a, b, c], [wa, wb, wc]) = weights
+        .iter()
+        .flatten()
+        .find(|(_, weights)| weights.iter().all(|weight| *weight >= -1e-4))
+        .or_else(|| weights.iter().flatten().next())
+        .copied()
+        .unwrap_or(([0, 1, 2], [1.0, 0.0, 0.0]));
+
+    [
+        values[a][0] * wa + values[b][0] * wb + values[c][0] * wc,
+        values[a][1] * wa + values[b][1] * wb + values[c][1] * wc,
+    ]
+}
+
+#[test]
+fn test_clip_polygon() {
+    let square = [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]];
+    let offset = [[1.0, 1.0], [1.0, 3.0], [3.0, 3.0], [3.0, 1.0]];
+    let clipped = clip_polygon(&square, &offset);
+    assert_eq!(4, clipped.len());
+    assert!((signed_area(&clipped).abs() - 1.0).abs() < 1e-6);
+
+    let outside = [[5.0, 5.0], [6.0, 5.0], [6.0, 6.0]];
+    assert!(clip_polygon(&outside, &square).is_empty());
+}
+
+#[test]
+fn test_interpolate_quad() {
+    let corners = [[0.0, 0.0], [0.0, 2.0], [2.0, 2.0], [2.0, 0.0]];
+    let values = [[0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0]];
+    assert_eq!([0.5, 0.5], interpolate_quad(&corners, &values, [1.0, 1.0]));
+    assert_eq!(
+        [0.25, 0.75],
+        interpolate_quad(&corners, &values, [0.5, 1.5])
+    );
+}
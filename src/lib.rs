@@ -2,26 +2,32 @@ mod bspfile;
 pub mod data;
 pub mod error;
 mod handle;
+#[cfg(feature = "mdl")]
+pub mod mdl;
+mod options;
 mod reader;
+mod validate;
 
-use crate::bspfile::LumpType;
+pub use crate::bspfile::LumpType;
 pub use crate::data::TextureFlags;
 pub use crate::data::Vector;
 pub use crate::data::*;
-use crate::error::ValidationError;
 pub use crate::handle::Handle;
+pub use crate::options::ReadOptions;
 use binrw::io::Cursor;
 use binrw::{BinRead, BinReaderExt};
 use bspfile::BspFile;
-pub use error::{BspError, StringError};
+pub use error::{BspError, BspWarning, StringError};
 use lzma_rs::decompress::{Options, UnpackedSize};
 use reader::LumpReader;
 use std::cmp::min;
-use std::{io::Read, ops::Deref};
+use std::io::Read;
+use std::ops::Deref;
+use std::sync::Arc;
 
 pub type BspResult<T> = Result<T, BspError>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Leaves {
     leaves: Vec<Leaf>,
 }
@@ -157,6 +163,10 @@ impl<'a> IntoIterator for &'a mut Leaves {
 #[derive(Debug)]
 pub struct Bsp {
     pub header: Header,
+    /// Revision of the map, incremented every time the map is saved in hammer
+    pub map_revision: u32,
+    pub directories: Directories,
+    pub map_flags: MapFlags,
     pub entities: Entities,
     pub textures_data: Vec<TextureData>,
     pub textures_info: Vec<TextureInfo>,
@@ -167,6 +177,8 @@ pub struct Bsp {
     pub leaves: Leaves,
     pub leaf_faces: Vec<LeafFace>,
     pub leaf_brushes: Vec<LeafBrush>,
+    pub leaf_water_data: Vec<LeafWaterData>,
+    pub leaf_minimum_distance_to_water: Vec<u16>,
     pub models: Vec<Model>,
     pub brushes: Vec<Brush>,
     pub brush_sides: Vec<BrushSide>,
@@ -175,94 +187,343 @@ pub struct Bsp {
     pub surface_edges: Vec<SurfaceEdge>,
     pub faces: Vec<Face>,
     pub original_faces: Vec<Face>,
+    pub face_ids: Vec<FaceId>,
+    pub face_macro_textures: Vec<FaceMacroTextureInfo>,
+    /// The variant of the faces, lighting and world lights that was loaded
+    ///
+    /// This differs from the requested variant if the bsp doesn't contain the requested lighting
+    pub lighting_mode: LightingMode,
+    /// The lighting variants contained in the bsp
+    pub available_lighting: LightingModes,
+    /// Lightmap samples, indexed by the `light_offset` of faces
+    pub lighting: Vec<ColorExp>,
+    pub world_lights: Vec<WorldLight>,
     pub vis_data: VisData,
     pub displacements: Vec<DisplacementInfo>,
     pub displacement_vertices: Vec<DisplacementVertex>,
     pub displacement_triangles: Vec<DisplacementTriangle>,
+    /// Multiblend data for the vertices of displacements using multiblend
+    pub displacement_multiblend: Vec<DisplacementMultiBlend>,
+    /// Offset into `displacement_multiblend` for each displacement
+    ///
+    /// Multiblend data is only stored for the displacements that use it, so the offsets are
+    /// computed when reading
+    pub displacement_multiblend_offsets: Vec<usize>,
+    pub displacement_lightmap_alphas: Vec<u8>,
+    /// Encoded lightmap sample positions, decoded by `Handle<DisplacementInfo>::lightmap_samples`
+    pub displacement_lightmap_sample_positions: Vec<u8>,
+    /// Collision data for each displacement, empty for displacements without collision
+    pub displacement_physics: Vec<PhysicsSolid>,
+    pub physics_models: Vec<PhysicsModel>,
+    pub primitives: Vec<Primitive>,
+    pub primitive_vertices: Vec<Vertex>,
+    pub primitive_indices: Vec<u16>,
+    pub cubemaps: Vec<CubeMap>,
+    pub occlusion: Occlusion,
+    pub overlays: Vec<Overlay>,
+    pub water_overlays: Vec<WaterOverlay>,
+    pub overlay_fades: Vec<OverlayFade>,
+    pub overlay_system_levels: Vec<OverlaySystemLevel>,
+    pub game_lump_header: GameLumpHeader,
+    pub game_lumps: Vec<RawGameLump>,
     pub static_props: PropStaticGameLump,
     pub pack: Packfile,
+    /// Non-fatal problems encountered while reading the bsp
+    pub warnings: Vec<BspWarning>,
 }
 
 impl Bsp {
+    /// Read a bsp file
+    ///
+    /// The data is validated strictly: out of range references, invalid displacements,
+    /// cycles in the node tree and malformed visibility data are all errors.
+    /// Older or hand-edited maps might not pass these checks, use
+    /// [`ReadOptions::lenient`] with [`read_with`](Self::read_with) to read them anyway.
     pub fn read(data: &[u8]) -> BspResult<Self> {
-        let bsp_file = BspFile::new(data)?;
-
-        let entities = bsp_file.lump_reader(LumpType::Entities)?.read_entities()?;
-        let textures_data = bsp_file
-            .lump_reader(LumpType::TextureData)?
-            .read_vec(|r| r.read())?;
-        let textures_info = bsp_file
-            .lump_reader(LumpType::TextureInfo)?
-            .read_vec(|r| r.read())?;
-        let texture_string_tables = bsp_file
-            .lump_reader(LumpType::TextureDataStringTable)?
-            .read_vec(|r| r.read())?;
-        let texture_string_data = String::from_utf8(
-            bsp_file
-                .get_lump(LumpType::TextureDataStringData)?
-                .1
-                .into_owned(),
-        )
-        .map_err(|e| BspError::String(StringError::NonUTF8(e.utf8_error())))?;
-        let planes = bsp_file
-            .lump_reader(LumpType::Planes)?
-            .read_vec(|r| r.read())?;
-        let nodes = bsp_file
-            .lump_reader(LumpType::Nodes)?
-            .read_vec(|r| r.read())?;
-        let leaves = bsp_file
-            .lump_reader(LumpType::Leaves)?
-            .read_vec_ver(|r, ver| r.read_args(ver))?
-            .into();
-        let leaf_faces = bsp_file
-            .lump_reader(LumpType::LeafFaces)?
-            .read_vec(|r| r.read())?;
-        let leaf_brushes = bsp_file
-            .lump_reader(LumpType::LeafBrushes)?
-            .read_vec(|r| r.read())?;
-        let models = bsp_file
-            .lump_reader(LumpType::Models)?
-            .read_vec(|r| r.read())?;
-        let brushes = bsp_file
-            .lump_reader(LumpType::Brushes)?
-            .read_vec(|r| r.read())?;
-        let brush_sides = bsp_file
-            .lump_reader(LumpType::BrushSides)?
-            .read_vec(|r| r.read())?;
-        let vertices = bsp_file
-            .lump_reader(LumpType::Vertices)?
-            .read_vec(|r| r.read())?;
-        let edges = bsp_file
-            .lump_reader(LumpType::Edges)?
-            .read_vec(|r| r.read())?;
-        let surface_edges = bsp_file
-            .lump_reader(LumpType::SurfaceEdges)?
-            .read_vec(|r| r.read())?;
-        let faces = bsp_file
-            .lump_reader(LumpType::Faces)?
-            .read_vec(|r| r.read())?;
-        let original_faces = bsp_file
-            .lump_reader(LumpType::OriginalFaces)?
-            .read_vec(|r| r.read())?;
-        let vis_data = bsp_file.lump_reader(LumpType::Visibility)?.read_visdata()?;
-        let displacements = bsp_file
-            .lump_reader(LumpType::DisplacementInfo)?
-            .read_vec(|r| r.read())?;
-        let displacement_vertices = bsp_file
-            .lump_reader(LumpType::DisplacementVertices)?
-            .read_vec(|r| r.read())?;
-        let displacement_triangles = bsp_file
-            .lump_reader(LumpType::DisplacementTris)?
-            .read_vec(|r| r.read())?;
-        let game_lumps: GameLumpHeader = bsp_file.lump_reader(LumpType::GameLump)?.read()?;
-        let pack = Packfile::read(bsp_file.lump_reader(LumpType::PakFile)?.into_data())?;
-
-        let static_props = game_lumps
-            .find(data)
-            .ok_or(ValidationError::NoStaticPropLump)??;
-
-        let bsp = Bsp {
+        Self::read_with(data, ReadOptions::default())
+    }
+
+    /// Read a bsp file with non-default options
+    pub fn read_with(data: &[u8], options: ReadOptions) -> BspResult<Self> {
+        Self::read_inner(data, None, options)
+    }
+
+    /// Read a bsp file from a reader
+    ///
+    /// The bsp is read from the current position of the reader until the end.
+    /// The file is read into memory once, the packfile is loaded from that buffer without copying
+    pub fn from_reader<R: Read>(reader: R) -> BspResult<Self> {
+        Self::from_reader_with(reader, ReadOptions::default())
+    }
+
+    /// Read a bsp file from a reader with non-default options
+    pub fn from_reader_with<R: Read>(mut reader: R, options: ReadOptions) -> BspResult<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::from_shared_with(Arc::new(data), options)
+    }
+
+    /// Read a bsp file from a shared buffer, such as a memory mapped file
+    ///
+    /// Instead of being copied, the packfile keeps a reference to the buffer if it isn't compressed
+    pub fn from_shared(data: SharedData) -> BspResult<Self> {
+        Self::from_shared_with(data, ReadOptions::default())
+    }
+
+    /// Read a bsp file from a shared buffer with non-default options
+    pub fn from_shared_with(data: SharedData, options: ReadOptions) -> BspResult<Self> {
+        Self::read_inner(data.as_ref().as_ref(), Some(&data), options)
+    }
+
+    fn read_inner(
+        data: &[u8],
+        shared: Option<&SharedData>,
+        options: ReadOptions,
+    ) -> BspResult<Self> {
+        let bsp_file = BspFile::new(data)?.with_lump_files(&options.lump_files);
+
+        let mut warnings = Vec::new();
+        for lump_file in &options.lump_files {
+            match lump_file.lump() {
+                Some(lump) if lump_file.header.map_revision != bsp_file.map_revision() => warnings
+                    .push(BspWarning::LumpFileRevisionMismatch {
+                        lump,
+                        lump_file_revision: lump_file.header.map_revision,
+                        map_revision: bsp_file.map_revision(),
+                    }),
+                Some(_) => {}
+                None => warnings.push(BspWarning::UnknownLumpFile(lump_file.header.lump_id)),
+            }
+        }
+
+        let mut available_lighting = LightingModes::empty();
+        if bsp_file.has_lump(LumpType::Lighting) {
+            available_lighting |= LightingModes::LDR;
+        }
+        if bsp_file.has_lump(LumpType::LightingHdr) {
+            available_lighting |= LightingModes::HDR;
+        }
+        let lighting_mode = match options.lighting {
+            mode if available_lighting.is_empty() => mode,
+            mode if available_lighting.contains(mode.into()) => mode,
+            LightingMode::Ldr => LightingMode::Hdr,
+            LightingMode::Hdr => LightingMode::Ldr,
+        };
+        let (faces_lump, lighting_lump, world_lights_lump) = match lighting_mode {
+            LightingMode::Ldr => (LumpType::Faces, LumpType::Lighting, LumpType::WorldLights),
+            LightingMode::Hdr => (
+                // ldr faces are used if the map doesn't contain a separate set of hdr faces
+                if bsp_file.has_lump(LumpType::FacesHdr) {
+                    LumpType::FacesHdr
+                } else {
+                    LumpType::Faces
+                },
+                LumpType::LightingHdr,
+                LumpType::WorldLightsHdr,
+            ),
+        };
+
+        // in lenient mode, lumps that fail to read are replaced by their default value
+        macro_rules! lump {
+            ($lump:expr, $read:expr) => {{
+                #[allow(clippy::redundant_closure_call)]
+                let result: BspResult<_> = (|| $read)();
+                match result {
+                    Ok(value) => value,
+                    Err(error) if options.lenient => {
+                        warnings.push(BspWarning::InvalidLump {
+                            lump: $lump,
+                            error: Arc::new(error),
+                        });
+                        Default::default()
+                    }
+                    Err(error) => return Err(error),
+                }
+            }};
+        }
+
+        // every lump is read independently, in parallel if the rayon feature is enabled,
+        // before the results are checked in order
+        macro_rules! read_lumps {
+            ($($name:ident = $lump:expr => $read:expr;)*) => {
+                #[cfg(feature = "rayon")]
+                let ($($name,)*) = {
+                    $(let mut $name = None;)*
+                    rayon::scope(|scope| {
+                        $(scope.spawn(|_| $name = Some((|| -> BspResult<_> { $read })()));)*
+                    });
+                    ($($name.expect("every lump is read"),)*)
+                };
+                #[cfg(not(feature = "rayon"))]
+                let ($($name,)*) = ($((|| -> BspResult<_> { $read })(),)*);
+                $(let $name = lump!($lump, $name);)*
+            };
+        }
+
+        read_lumps! {
+            map_flags = LumpType::MapFlags => {
+                if bsp_file.has_lump(LumpType::MapFlags) {
+                    bsp_file.lump_reader(LumpType::MapFlags)?.read()
+                } else {
+                    Ok(MapFlags::default())
+                }
+            };
+            entities = LumpType::Entities => bsp_file
+                .lump_reader(LumpType::Entities)?
+                .read_entities();
+            textures_data = LumpType::TextureData => bsp_file
+                .lump_reader(LumpType::TextureData)?
+                .read_vec(|r| r.read());
+            textures_info = LumpType::TextureInfo => bsp_file
+                .lump_reader(LumpType::TextureInfo)?
+                .read_vec(|r| r.read());
+            texture_string_tables = LumpType::TextureDataStringTable => bsp_file
+                .lump_reader(LumpType::TextureDataStringTable)?
+                .read_vec(|r| r.read());
+            texture_string_data = LumpType::TextureDataStringData => {
+                String::from_utf8(
+                    bsp_file
+                        .get_lump(LumpType::TextureDataStringData)?
+                        .1
+                        .into_owned(),
+                )
+                .map_err(|e| BspError::String(StringError::NonUTF8(e.utf8_error())))
+            };
+            planes = LumpType::Planes => bsp_file.lump_reader(LumpType::Planes)?.read_pod_vec();
+            nodes = LumpType::Nodes => bsp_file.lump_reader(LumpType::Nodes)?.read_pod_vec();
+            leaves = LumpType::Leaves => bsp_file
+                .lump_reader(LumpType::Leaves)?
+                .read_vec_ver(|r, ver| r.read_args(ver))
+                .map(Leaves::from);
+            leaf_faces = LumpType::LeafFaces => bsp_file
+                .lump_reader(LumpType::LeafFaces)?
+                .read_pod_vec();
+            leaf_brushes = LumpType::LeafBrushes => bsp_file
+                .lump_reader(LumpType::LeafBrushes)?
+                .read_vec(|r| r.read());
+            leaf_water_data = LumpType::LeafWaterData => bsp_file
+                .lump_reader(LumpType::LeafWaterData)?
+                .read_vec(|r| r.read());
+            leaf_minimum_distance_to_water = LumpType::LeafMinimumDistanceToWater => bsp_file
+                .lump_reader(LumpType::LeafMinimumDistanceToWater)?
+                .read_vec(|r| r.read());
+            models = LumpType::Models => bsp_file.lump_reader(LumpType::Models)?.read_pod_vec();
+            brushes = LumpType::Brushes => bsp_file
+                .lump_reader(LumpType::Brushes)?
+                .read_vec(|r| r.read());
+            brush_sides = LumpType::BrushSides => bsp_file
+                .lump_reader(LumpType::BrushSides)?
+                .read_vec(|r| r.read());
+            vertices = LumpType::Vertices => bsp_file
+                .lump_reader(LumpType::Vertices)?
+                .read_pod_vec();
+            edges = LumpType::Edges => bsp_file.lump_reader(LumpType::Edges)?.read_pod_vec();
+            surface_edges = LumpType::SurfaceEdges => bsp_file
+                .lump_reader(LumpType::SurfaceEdges)?
+                .read_pod_vec();
+            faces = faces_lump => bsp_file.lump_reader(faces_lump)?.read_pod_vec();
+            lighting = lighting_lump => bsp_file.lump_reader(lighting_lump)?.read_vec(|r| r.read());
+            world_lights = world_lights_lump => bsp_file
+                .lump_reader(world_lights_lump)?
+                .read_world_lights();
+            original_faces = LumpType::OriginalFaces => bsp_file
+                .lump_reader(LumpType::OriginalFaces)?
+                .read_pod_vec();
+            face_ids = LumpType::FaceIds => bsp_file
+                .lump_reader(LumpType::FaceIds)?
+                .read_vec(|r| r.read());
+            face_macro_textures = LumpType::FaceMacroTextureInfo => bsp_file
+                .lump_reader(LumpType::FaceMacroTextureInfo)?
+                .read_vec(|r| r.read());
+            vis_data = LumpType::Visibility => bsp_file
+                .lump_reader(LumpType::Visibility)?
+                .read_visdata();
+            displacements = LumpType::DisplacementInfo => bsp_file
+                .lump_reader(LumpType::DisplacementInfo)?
+                .read_vec(|r| r.read());
+            displacement_vertices = LumpType::DisplacementVertices => bsp_file
+                .lump_reader(LumpType::DisplacementVertices)?
+                .read_vec(|r| r.read());
+            displacement_triangles = LumpType::DisplacementTris => bsp_file
+                .lump_reader(LumpType::DisplacementTris)?
+                .read_vec(|r| r.read());
+            displacement_multiblend = LumpType::DisplacementMultiBlend => bsp_file
+                .lump_reader(LumpType::DisplacementMultiBlend)?
+                .read_vec(|r| r.read());
+            displacement_lightmap_alphas = LumpType::DisplacementLightMapAlphas => {
+                Ok(bsp_file
+                    .get_lump(LumpType::DisplacementLightMapAlphas)?
+                    .1
+                    .into_owned())
+            };
+            displacement_lightmap_sample_positions =
+                LumpType::DisplacementLightMapSamplePositions => {
+                    Ok(bsp_file
+                        .get_lump(LumpType::DisplacementLightMapSamplePositions)?
+                        .1
+                        .into_owned())
+                };
+            displacement_physics = LumpType::PhysDisplacement => bsp_file
+                .lump_reader(LumpType::PhysDisplacement)?
+                .read_displacement_physics();
+            physics_models = LumpType::PhysCollide => bsp_file
+                .lump_reader(LumpType::PhysCollide)?
+                .read_physics_models();
+            primitives = LumpType::Primitives => bsp_file
+                .lump_reader(LumpType::Primitives)?
+                .read_vec(|r| r.read());
+            primitive_vertices = LumpType::PrimVertices => bsp_file
+                .lump_reader(LumpType::PrimVertices)?
+                .read_vec(|r| r.read());
+            primitive_indices = LumpType::PrimIndices => bsp_file
+                .lump_reader(LumpType::PrimIndices)?
+                .read_vec(|r| r.read());
+            cubemaps = LumpType::CubeMaps => bsp_file
+                .lump_reader(LumpType::CubeMaps)?
+                .read_vec(|r| r.read());
+            occlusion = LumpType::Occlusion => bsp_file
+                .lump_reader(LumpType::Occlusion)?
+                .read_occlusion();
+            overlays = LumpType::Overlays => bsp_file
+                .lump_reader(LumpType::Overlays)?
+                .read_vec(|r| r.read());
+            water_overlays = LumpType::WaterOverlays => bsp_file
+                .lump_reader(LumpType::WaterOverlays)?
+                .read_vec(|r| r.read());
+            overlay_fades = LumpType::OverlayFades => bsp_file
+                .lump_reader(LumpType::OverlayFades)?
+                .read_vec(|r| r.read());
+            overlay_system_levels = LumpType::OverlaySystemLevels => bsp_file
+                .lump_reader(LumpType::OverlaySystemLevels)?
+                .read_vec(|r| r.read());
+            game_lumps = LumpType::GameLump => {
+                let header: GameLumpHeader = bsp_file.lump_reader(LumpType::GameLump)?.read()?;
+                let (game_lump_data, data_offset) = bsp_file.game_lump_data();
+                let lumps = header.read_lumps(game_lump_data, data_offset);
+                let static_props = lumps.iter().find_map(RawGameLump::parse);
+                Ok((header, lumps, static_props))
+            };
+            pack = LumpType::PakFile => {
+                match (shared, bsp_file.lump_range(LumpType::PakFile)) {
+                    (Some(shared), Some(range)) => Packfile::read_shared(shared.clone(), range),
+                    _ => Packfile::read(bsp_file.lump_reader(LumpType::PakFile)?.into_data()),
+                }
+            };
+        }
+        let (game_lump_header, game_lumps, static_props) = game_lumps;
+        let static_props = match static_props {
+            Some(static_props) => lump!(LumpType::GameLump, static_props),
+            None => {
+                warnings.push(BspWarning::NoStaticPropLump);
+                PropStaticGameLump::default()
+            }
+        };
+
+        let mut bsp = Bsp {
             header: bsp_file.header().clone(),
+            map_revision: bsp_file.map_revision(),
+            directories: bsp_file.directories().clone(),
+            map_flags,
             entities,
             textures_data,
             textures_info,
@@ -273,6 +534,8 @@ impl Bsp {
             leaves,
             leaf_faces,
             leaf_brushes,
+            leaf_water_data,
+            leaf_minimum_distance_to_water,
             models,
             brushes,
             brush_sides,
@@ -281,17 +544,68 @@ impl Bsp {
             surface_edges,
             faces,
             original_faces,
+            face_ids,
+            face_macro_textures,
+            lighting_mode,
+            available_lighting,
+            lighting,
+            world_lights,
             vis_data,
             displacements,
             displacement_vertices,
             displacement_triangles,
+            displacement_multiblend,
+            displacement_multiblend_offsets: Vec::new(),
+            displacement_lightmap_alphas,
+            displacement_lightmap_sample_positions,
+            displacement_physics,
+            physics_models,
+            primitives,
+            primitive_vertices,
+            primitive_indices,
+            cubemaps,
+            occlusion,
+            overlays,
+            water_overlays,
+            overlay_fades,
+            overlay_system_levels,
+            game_lump_header,
+            game_lumps,
             static_props,
             pack,
+            warnings,
         };
-        bsp.validate()?;
+        bsp.validate(options.lenient)?;
+        // the displacement powers are only valid after validation
+        bsp.displacement_multiblend_offsets = multiblend_offsets(&bsp.displacements);
         Ok(bsp)
     }
 
+    /// Get a string from the texture string table
+    ///
+    /// Negative offsets in the table are used for empty strings
+    fn texture_string(&self, table_index: usize) -> Option<&str> {
+        let start = match usize::try_from(*self.texture_string_tables.get(table_index)?) {
+            Ok(start) => start,
+            Err(_) => return Some(""),
+        };
+        let part = self.texture_string_data.get(start..)?;
+        Some(match part.split_once('\0') {
+            Some((s, _)) => s,
+            None => part,
+        })
+    }
+
+    /// Create a lump file replacing a lump of this map
+    pub fn lump_file(&self, lump: LumpType, data: Vec<u8>) -> LumpFile {
+        LumpFile::new(lump, self.lump_version(lump), self.map_revision, data)
+    }
+
+    /// Get the version of a lump
+    pub fn lump_version(&self, lump: LumpType) -> u32 {
+        self.directories[lump].version
+    }
+
     pub fn leaf(&self, n: usize) -> Option<Handle<'_, Leaf>> {
         self.leaves.get(n).map(|leaf| Handle::new(self, leaf))
     }
@@ -321,8 +635,17 @@ impl Bsp {
     }
 
     /// Get the root node of the bsp
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bsp has no nodes, see [`try_root_node`](Self::try_root_node)
     pub fn root_node(&self) -> Handle<'_, Node> {
-        self.node(0).unwrap()
+        self.try_root_node().expect("bsp has no root node")
+    }
+
+    /// Get the root node of the bsp, `None` if the bsp has no nodes
+    pub fn try_root_node(&self) -> Option<Handle<'_, Node>> {
+        self.node(0)
     }
 
     /// Get all models stored in the bsp
@@ -336,11 +659,14 @@ impl Bsp {
     }
 
     /// Find the index of the leaf for a specific position
+    ///
+    /// Returns `None` if the bsp tree references nodes or planes out of range
     pub fn leaf_index_at(&self, point: Vector) -> Option<usize> {
-        let mut current = self.root_node();
+        let mut current = self.try_root_node()?;
 
-        loop {
-            let plane = current.plane();
+        // a valid tree never visits more nodes than it has
+        for _ in 0..self.nodes.len() {
+            let plane = current.try_plane()?;
 
             let dot = match plane.ty {
                 // plane types < 3 are like a one hot normal
@@ -361,14 +687,34 @@ impl Bsp {
             if next < 0 {
                 return Some((!next) as usize);
             } else {
-                current = self.node(next as usize).unwrap();
+                current = self.node(next as usize)?;
             }
         }
+        None
     }
 
     /// Find a leaf for a specific position
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bsp tree is invalid, see [`try_leaf_at`](Self::try_leaf_at)
     pub fn leaf_at(&self, point: Vector) -> Handle<'_, Leaf> {
-        self.leaf(self.leaf_index_at(point).unwrap()).unwrap()
+        self.try_leaf_at(point).expect("invalid bsp tree")
+    }
+
+    /// Find a leaf for a specific position, `None` if the bsp tree references data out of range
+    pub fn try_leaf_at(&self, point: Vector) -> Option<Handle<'_, Leaf>> {
+        self.leaf(self.leaf_index_at(point)?)
+    }
+
+    /// Get the water surface above a point
+    ///
+    /// The returned water data contains the height of the surface and its texture.
+    /// Returns `None` if the point isn't under water
+    pub fn water_surface_at(&self, point: Vector) -> Option<Handle<'_, LeafWaterData>> {
+        let leaf = self.leaf(self.leaf_index_at(point)?)?;
+        let water = leaf.water_data()?;
+        (point.z <= water.surface_z).then_some(water)
     }
 
     pub fn static_props(&self) -> impl Iterator<Item = Handle<'_, StaticPropLump>> {
@@ -379,167 +725,101 @@ impl Bsp {
             .map(|lump| Handle::new(self, lump))
     }
 
-    /// Get all faces stored in the bsp
-    pub fn original_faces(&self) -> impl Iterator<Item = Handle<Face>> {
-        self.faces.iter().map(move |face| Handle::new(self, face))
-    }
-
-    fn validate(&self) -> BspResult<()> {
-        self.validate_indexes(
-            self.faces
-                .iter()
-                .filter_map(|face| face.displacement_index()),
-            &self.displacements,
-            "face",
-            "displacement",
-        )?;
-        self.validate_indexes(
-            self.displacements
-                .iter()
-                .map(|displacement| displacement.map_face),
-            &self.faces,
-            "displacement",
-            "face",
-        )?;
-        self.validate_indexes(
-            self.faces
-                .iter()
-                .map(|face| face.first_edge + face.num_edges as i32 - 1),
-            &self.surface_edges,
-            "face",
-            "surface_edge",
-        )?;
-        self.validate_indexes(
-            self.surface_edges.iter().map(|edge| edge.edge_index()),
-            &self.edges,
-            "surface_edge",
-            "edge",
-        )?;
-        self.validate_indexes(
-            self.edges
-                .iter()
-                .flat_map(|edge| [edge.start_index, edge.end_index]),
-            &self.vertices,
-            "edge",
-            "vertex",
-        )?;
-        self.validate_indexes(
-            self.displacements
-                .iter()
-                .flat_map(|displacement| &displacement.corner_neighbours)
-                .flat_map(|corner| corner.neighbours()),
-            &self.displacements,
-            "displacement",
-            "displacement",
-        )?;
-        self.validate_indexes(
-            self.displacements
-                .iter()
-                .flat_map(|displacement| &displacement.edge_neighbours)
-                .flat_map(|edge| edge.iter())
-                .map(|sub| sub.neighbour_index),
-            &self.displacements,
-            "displacement",
-            "displacement",
-        )?;
-        self.validate_indexes(
-            self.faces.iter().map(|face| face.texture_info),
-            &self.textures_info,
-            "face",
-            "texture_info",
-        )?;
-        self.validate_indexes(
-            self.textures_info
-                .iter()
-                .map(|texture| texture.texture_data_index),
-            &self.textures_data,
-            "texture_info",
-            "texture_data",
-        )?;
-        self.validate_indexes(
-            self.textures_data
-                .iter()
-                .map(|texture| texture.name_string_table_id),
-            &self.texture_string_tables,
-            "textures_data",
-            "texture_string_tables",
-        )?;
-        self.validate_indexes(
-            self.texture_string_tables.iter().copied(),
-            self.texture_string_data.as_bytes(),
-            "texture_string_tables",
-            "texture_string_data",
-        )?;
-        self.validate_indexes(
-            self.nodes.iter().map(|node| node.plane_index),
-            &self.planes,
-            "node",
-            "plane",
-        )?;
-        self.validate_indexes(
-            self.nodes
-                .iter()
-                .flat_map(|node| node.children)
-                .filter(|index| *index >= 0),
-            &self.nodes,
-            "node",
-            "node",
-        )?;
-        self.validate_indexes(
-            self.nodes
-                .iter()
-                .flat_map(|node| node.children)
-                .filter_map(|index| (index < 0).then_some(!index)),
-            &self.leaves,
-            "node",
-            "leaf",
-        )?;
-        self.validate_indexes(
-            self.static_props().map(|prop| prop.prop_type),
-            &self.static_props.dict.name,
-            "static props",
-            "static prop models",
-        )?;
-
-        if self.nodes.is_empty() {
-            return Err(ValidationError::NoRootNode.into());
+    /// Get all static props that are potentially visible from a specific position
+    ///
+    /// Returns `None` if the position is not inside a visible cluster or the visibility data is malformed
+    pub fn visible_static_props(
+        &self,
+        point: Vector,
+    ) -> Option<impl Iterator<Item = Handle<'_, StaticPropLump>>> {
+        let cluster = self.try_leaf_at(point)?.cluster;
+        if cluster < 0 {
+            return None;
         }
+        let visible_clusters = self.vis_data.visible_clusters(cluster).ok()?;
+        let is_visible = move |leaf: &Leaf| {
+            leaf.cluster == cluster
+                || (leaf.cluster >= 0
+                    && (leaf.cluster as u64) < visible_clusters.len()
+                    && visible_clusters[leaf.cluster as u64])
+        };
 
-        for face in &self.faces {
-            if face.displacement_index().is_some() && face.num_edges != 4 {
-                return Err(ValidationError::NonSquareDisplacement(face.num_edges).into());
-            }
-        }
+        Some(
+            self.static_props()
+                .filter(move |prop| prop.leaves().any(|leaf| is_visible(&leaf))),
+        )
+    }
 
-        Ok(())
-    }
-
-    fn validate_indexes<
-        'b,
-        Index: TryInto<usize> + Into<i64> + Copy + Ord + Default,
-        Indexes: Iterator<Item = Index>,
-        T: 'b,
-    >(
-        &'b self,
-        indexes: Indexes,
-        list: &[T],
-        source: &'static str,
-        target: &'static str,
-    ) -> BspResult<()> {
-        let max = match indexes.max() {
-            Some(max) => max,
-            None => return Ok(()),
-        };
-        max.try_into()
-            .ok()
-            .and_then(|index| list.get(index))
-            .ok_or_else(|| ValidationError::ReferenceOutOfRange {
-                source_: source,
-                target,
-                index: max.into(),
-                size: list.len(),
-            })?;
-        Ok(())
+    /// Get the collision data for a model
+    pub fn physics_model(&self, model_index: usize) -> Option<&PhysicsModel> {
+        self.physics_models
+            .iter()
+            .find(|model| model.model_index as usize == model_index)
+    }
+
+    /// Get all cubemaps stored in the bsp
+    pub fn cubemaps(&self) -> impl Iterator<Item = Handle<'_, CubeMap>> {
+        self.cubemaps
+            .iter()
+            .map(move |cubemap| Handle::new(self, cubemap))
+    }
+
+    /// Find the cubemap closest to a specific position
+    pub fn nearest_cubemap(&self, point: Vector) -> Option<Handle<'_, CubeMap>> {
+        self.cubemaps().min_by(|a, b| {
+            (a.position() - point)
+                .length_squared()
+                .total_cmp(&(b.position() - point).length_squared())
+        })
+    }
+
+    /// Get all occluders stored in the bsp
+    pub fn occluders(&self) -> impl Iterator<Item = Handle<'_, Occluder>> {
+        self.occlusion
+            .occluders
+            .iter()
+            .map(move |occluder| Handle::new(self, occluder))
+    }
+
+    /// Check if the line of sight between two points is blocked by an active occluder
+    pub fn is_occluded(&self, eye: Vector, target: Vector) -> bool {
+        self.occluders()
+            .filter(|occluder| occluder.is_active())
+            .flat_map(|occluder| occluder.polygons())
+            .any(|polygon| polygon.intersects(eye, target))
+    }
+
+    /// Get all overlays stored in the bsp
+    pub fn overlays(&self) -> impl Iterator<Item = Handle<'_, Overlay>> {
+        self.overlays
+            .iter()
+            .map(move |overlay| Handle::new(self, overlay))
+    }
+
+    /// Get all water overlays stored in the bsp
+    pub fn water_overlays(&self) -> impl Iterator<Item = Handle<'_, WaterOverlay>> {
+        self.water_overlays
+            .iter()
+            .map(move |overlay| Handle::new(self, overlay))
+    }
+
+    /// Get all game lumps stored in the bsp
+    pub fn game_lumps(&self) -> impl Iterator<Item = &RawGameLump> {
+        self.game_lumps.iter()
+    }
+
+    /// Find and parse a game lump by type
+    ///
+    /// Returns `None` if the bsp doesn't contain a game lump with the id of the type
+    pub fn game_lump<T: GameLumpType<Args<'static> = (u16,)>>(&self) -> Option<BspResult<T>> {
+        self.game_lumps.iter().find_map(RawGameLump::parse)
+    }
+
+    /// Get all original faces stored in the bsp, before they were split during compilation
+    pub fn original_faces(&self) -> impl Iterator<Item = Handle<'_, Face>> {
+        self.original_faces
+            .iter()
+            .map(move |face| Handle::new(self, face))
     }
 }
 
@@ -582,7 +862,7 @@ fn lzma_decompress_with_header(data: &[u8], expected_length: usize) -> Result<Ve
 
 #[cfg(test)]
 mod tests {
-    use super::Bsp;
+    use super::{Bsp, BspWarning, EdgeDirection, LightingMode, LumpType, ReadOptions};
 
     #[test]
     fn tf2_file() {
@@ -592,4 +872,482 @@ mod tests {
 
         Bsp::read(&data).unwrap();
     }
+
+    #[test]
+    fn tf2_file_is_valid() {
+        use std::fs::read;
+
+        // the stricter validation shouldn't change anything for a real map
+        let data = read("koth_bagel_rc2a.bsp").unwrap();
+        let bsp = Bsp::read_with(&data, ReadOptions::default().lenient(true)).unwrap();
+        assert!(!bsp.warnings.iter().any(|warning| matches!(
+            warning,
+            BspWarning::InvalidLump { .. }
+                | BspWarning::InvalidElement { .. }
+                | BspWarning::Validation(_)
+        )));
+
+        for cluster in 0..bsp.vis_data.cluster_count {
+            bsp.vis_data.visible_clusters(cluster as i16).unwrap();
+        }
+        for index in 0..bsp.displacements.len() {
+            let displacement = bsp.displacement(index).unwrap();
+            assert!(displacement.face().is_some());
+            assert_eq!(
+                displacement.triangle_count() as usize,
+                displacement.triangles().count()
+            );
+        }
+    }
+
+    #[test]
+    fn from_reader() {
+        use std::io::{Cursor, Seek, SeekFrom};
+        use std::sync::Arc;
+
+        let data = test_map(vec![(LumpType::Faces, face(0, 0, -1))]);
+        let expected = format!("{:?}", Bsp::read(&data).unwrap());
+
+        // the bsp starts at the current position of the reader
+        let mut reader = Cursor::new([b"prefix".as_slice(), &data].concat());
+        reader.seek(SeekFrom::Start(6)).unwrap();
+        let bsp = Bsp::from_reader(reader).unwrap();
+        assert_eq!(expected, format!("{:?}", bsp));
+
+        let bsp = Bsp::from_shared(Arc::new(data)).unwrap();
+        assert_eq!(expected, format!("{:?}", bsp));
+    }
+
+    #[test]
+    fn compressed_lump_error() {
+        // a map without any lumps except for a compressed overlay lump that can't be decompressed
+        let header_size = 8 + 64 * 16 + 4;
+        let mut data = b"VBSP".to_vec();
+        data.extend_from_slice(&20u32.to_le_bytes());
+        for lump in LumpType::all() {
+            let entry: [u32; 4] = match lump {
+                LumpType::Overlays => [header_size, 8, 0, 1],
+                _ => [0; 4],
+            };
+            for value in entry {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(b"LZMAjunk");
+
+        assert!(Bsp::read(&data).is_err());
+
+        // only the broken lump is skipped in lenient mode
+        let bsp = Bsp::read_with(&data, ReadOptions::default().lenient(true)).unwrap();
+        assert!(bsp.warnings.iter().any(|warning| matches!(
+            warning,
+            BspWarning::InvalidLump {
+                lump: LumpType::Overlays,
+                ..
+            }
+        )));
+    }
+
+    fn bytes(values: &[i32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    /// Build a map with a single plane, node and leaf, with extra lumps added or replaced
+    fn test_map(extra: Vec<(LumpType, Vec<u8>)>) -> Vec<u8> {
+        let mut lumps = vec![
+            (
+                LumpType::Planes,
+                bytes(&[0, 0, 1f32.to_bits() as i32, 0, 2]),
+            ),
+            (LumpType::Nodes, bytes(&[0, -1, -1, 0, 0, 0, 0, 0])),
+            (LumpType::Leaves, vec![0; 32]),
+            (LumpType::GameLump, bytes(&[0])),
+            // an empty zip
+            (
+                LumpType::PakFile,
+                [&[0x50, 0x4b, 0x05, 0x06][..], &[0; 18]].concat(),
+            ),
+        ];
+        for (lump, data) in extra {
+            lumps.retain(|(existing, _)| *existing != lump);
+            lumps.push((lump, data));
+        }
+
+        let header_size = 8 + 64 * 16 + 4;
+        let mut directory = [[0; 4]; 64];
+        let mut lump_data = Vec::new();
+        for (lump, data) in lumps {
+            let offset = header_size + lump_data.len();
+            directory[lump as usize] = [offset as i32, data.len() as i32, 0, 0];
+            lump_data.extend_from_slice(&data);
+        }
+
+        let mut map = b"VBSP".to_vec();
+        map.extend_from_slice(&bytes(&[20]));
+        for entry in directory {
+            map.extend_from_slice(&bytes(&entry));
+        }
+        map.extend_from_slice(&bytes(&[1]));
+        map.extend_from_slice(&lump_data);
+        map
+    }
+
+    #[test]
+    fn cubemap_texture() {
+        use std::io::{Cursor, Write};
+        use zip::write::{FileOptions, ZipWriter};
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("materials/maps/koth_test/c0_-16_64.vtf", options)
+            .unwrap();
+        zip.write_all(b"ldr").unwrap();
+        zip.start_file("materials/maps/koth_test/c0_-16_64.hdr.vtf", options)
+            .unwrap();
+        zip.write_all(b"hdr").unwrap();
+        let pack = zip.finish().unwrap().into_inner();
+
+        let data = test_map(vec![
+            (LumpType::CubeMaps, bytes(&[0, -16, 64, 0])),
+            (LumpType::PakFile, pack),
+        ]);
+        let bsp = Bsp::read(&data).unwrap();
+        let cubemap = bsp.cubemaps().next().unwrap();
+
+        assert_eq!(Some("koth_test".into()), bsp.pack.map_name());
+        assert_eq!(
+            Some("materials/maps/koth_test/c0_-16_64.hdr.vtf".into()),
+            cubemap.texture_path(LightingMode::Hdr)
+        );
+        assert_eq!(
+            Some(b"ldr".to_vec()),
+            cubemap.texture(LightingMode::Ldr).unwrap()
+        );
+        assert_eq!(
+            Some(b"hdr".to_vec()),
+            cubemap.texture(LightingMode::Hdr).unwrap()
+        );
+    }
+
+    /// A face with the given edges and texture, without displacement
+    fn face(first_edge: i32, num_edges: i16, texture_info: i16) -> Vec<u8> {
+        let mut face = vec![0; 4];
+        face.extend_from_slice(&first_edge.to_le_bytes());
+        face.extend_from_slice(&num_edges.to_le_bytes());
+        face.extend_from_slice(&texture_info.to_le_bytes());
+        face.extend_from_slice(&(-1i16).to_le_bytes());
+        face.resize(56, 0);
+        face
+    }
+
+    #[test]
+    fn negative_texture_references() {
+        let data = test_map(vec![
+            (LumpType::Faces, face(0, 0, -1)),
+            (
+                LumpType::TextureInfo,
+                [
+                    bytes(&[0; 16]),
+                    bytes(&[0, -1]),
+                    bytes(&[0; 16]),
+                    bytes(&[0, 0]),
+                ]
+                .concat(),
+            ),
+            (LumpType::TextureData, bytes(&[0, 0, 0, 0, 0, 0, 0, 0])),
+            (LumpType::TextureDataStringTable, bytes(&[-1])),
+        ]);
+
+        // negative indexes mark missing references and are valid in strict mode
+        let bsp = Bsp::read(&data).unwrap();
+        assert_eq!("", bsp.face(0).unwrap().texture().name());
+        let names: Vec<_> = bsp.textures().map(|texture| texture.name()).collect();
+        assert_eq!(vec!["", ""], names);
+    }
+
+    #[test]
+    fn string_offset_inside_character() {
+        let data = test_map(vec![
+            (
+                LumpType::TextureInfo,
+                [bytes(&[0; 16]), bytes(&[0, 0])].concat(),
+            ),
+            (LumpType::TextureData, bytes(&[0, 0, 0, 0, 0, 0, 0, 0])),
+            (LumpType::TextureDataStringTable, bytes(&[1])),
+            (LumpType::TextureDataStringData, "é\0".as_bytes().to_vec()),
+        ]);
+
+        assert!(Bsp::read(&data).is_err());
+
+        let bsp = Bsp::read_with(&data, ReadOptions::default().lenient(true)).unwrap();
+        assert_eq!(vec![-1], bsp.texture_string_tables);
+        assert_eq!("", bsp.textures().next().unwrap().name());
+    }
+
+    #[test]
+    fn face_without_edges() {
+        let data = test_map(vec![(LumpType::Faces, face(5, 0, -1))]);
+
+        // the first edge of a face without edges is never used
+        let bsp = Bsp::read(&data).unwrap();
+        let face = bsp.face(0).unwrap();
+        assert_eq!(0, face.vertices().count());
+        assert_eq!(EdgeDirection::FirstToLast, face.edge_direction());
+
+        // the test map has no static prop game lump
+        assert!(bsp
+            .warnings
+            .iter()
+            .any(|warning| matches!(warning, BspWarning::NoStaticPropLump)));
+        assert_eq!(0, bsp.static_props().count());
+    }
+
+    #[test]
+    fn displacement_triangle_tags() {
+        use crate::{DisplacementTriangleFlags, Vector};
+
+        // a flat 4x4 square with a power 2 displacement
+        let vertices = [[0f32, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]]
+            .iter()
+            .flat_map(|[x, y]| [x.to_bits() as i32, y.to_bits() as i32, 0])
+            .collect::<Vec<_>>();
+        let mut face = face(0, 4, -1);
+        face[12..14].copy_from_slice(&0i16.to_le_bytes());
+        let mut displacement = vec![0; 176];
+        displacement[16..20].copy_from_slice(&1i32.to_le_bytes());
+        displacement[20..24].copy_from_slice(&2i32.to_le_bytes());
+        displacement[48..96].fill(0xFF);
+        // the tags start after an unrelated first tag, each triangle gets its index as tags
+        let tags: Vec<u8> = [0xFFu16]
+            .into_iter()
+            .chain(0..32)
+            .flat_map(u16::to_le_bytes)
+            .collect();
+
+        let data = test_map(vec![
+            (LumpType::Vertices, bytes(&vertices)),
+            (
+                LumpType::Edges,
+                bytes(&[1 << 16, 1 | (2 << 16), 2 | (3 << 16), 3]),
+            ),
+            (LumpType::SurfaceEdges, bytes(&[0, 1, 2, 3])),
+            (LumpType::Faces, face),
+            (LumpType::DisplacementInfo, displacement),
+            (LumpType::DisplacementVertices, vec![0; 25 * 20]),
+            (LumpType::DisplacementTris, tags),
+        ]);
+        let bsp = Bsp::read(&data).unwrap();
+        let triangles: Vec<_> = bsp.displacement(0).unwrap().triangles().collect();
+        assert_eq!(32, triangles.len());
+
+        for (i, triangle) in triangles.iter().enumerate() {
+            assert_eq!(
+                DisplacementTriangleFlags::from_bits_truncate(i as u16),
+                triangle.tags
+            );
+        }
+        let vertices = |i: usize| triangles[i].vertices.map(|Vector { x, y, .. }| [x, y]);
+        assert_eq!([[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]], vertices(0));
+        assert_eq!([[0.0, 0.0], [1.0, 1.0], [0.0, 1.0]], vertices(1));
+        assert_eq!([[0.0, 1.0], [1.0, 1.0], [0.0, 2.0]], vertices(2));
+        assert_eq!([[3.0, 3.0], [4.0, 4.0], [3.0, 4.0]], vertices(31));
+    }
+
+    #[test]
+    fn lenient_empty_target() {
+        // edges, surface edges and the node reference vertices and planes that don't exist
+        let data = test_map(vec![
+            (LumpType::Planes, Vec::new()),
+            (LumpType::Edges, bytes(&[1 << 16])),
+            (LumpType::SurfaceEdges, bytes(&[0, 0, 0])),
+            (LumpType::Faces, face(0, 3, 0)),
+        ]);
+
+        assert!(Bsp::read(&data).is_err());
+
+        // references that can't be clamped remove the elements and everything using them
+        let bsp = Bsp::read_with(&data, ReadOptions::default().lenient(true)).unwrap();
+        assert!(bsp.edges.is_empty());
+        assert!(bsp.surface_edges.is_empty());
+        assert!(bsp.nodes.is_empty());
+        assert!(bsp.try_root_node().is_none());
+        let face = bsp.face(0).unwrap();
+        assert_eq!(Some(true), face.try_is_visible());
+        assert_eq!(0, face.vertices().count());
+        assert_eq!("", face.texture().name());
+    }
+
+    #[test]
+    fn original_faces_and_face_ids() {
+        let mut split = face(0, 0, -1);
+        split[44..48].copy_from_slice(&0i32.to_le_bytes());
+        let mut unsplit = face(0, 0, -1);
+        unsplit[44..48].copy_from_slice(&(-1i32).to_le_bytes());
+
+        let data = test_map(vec![
+            (LumpType::Faces, [split, unsplit].concat()),
+            (LumpType::OriginalFaces, face(0, 0, -1)),
+            (
+                LumpType::FaceIds,
+                [7u16, 9].iter().flat_map(|id| id.to_le_bytes()).collect(),
+            ),
+        ]);
+        let bsp = Bsp::read(&data).unwrap();
+        assert_eq!(1, bsp.original_faces().count());
+
+        let split = bsp.face(0).unwrap();
+        let original = split.original().unwrap();
+        assert!(std::ptr::eq(&bsp.original_faces[0], &*original));
+        assert_eq!(Some(7), split.hammer_face_id());
+        assert_eq!(None, original.hammer_face_id());
+
+        let unsplit = bsp.face(1).unwrap();
+        assert!(unsplit.original().is_none());
+        assert_eq!(Some(9), unsplit.hammer_face_id());
+    }
+
+    #[test]
+    fn map_flags_and_revision() {
+        use crate::MapFlags;
+
+        let mut data = test_map(vec![(LumpType::MapFlags, bytes(&[0b101]))]);
+        let version_offset = 8 + LumpType::MapFlags as usize * 16 + 8;
+        data[version_offset..version_offset + 4].copy_from_slice(&3u32.to_le_bytes());
+        let revision_offset = 8 + 64 * 16;
+        data[revision_offset..revision_offset + 4].copy_from_slice(&42u32.to_le_bytes());
+
+        let bsp = Bsp::read(&data).unwrap();
+        assert_eq!(
+            MapFlags::BAKED_STATIC_PROP_LIGHTING_NON_HDR | MapFlags::LIGHTSTYLES_WITH_CSM,
+            bsp.map_flags
+        );
+        assert_eq!(42, bsp.map_revision);
+        assert_eq!(3, bsp.lump_version(LumpType::MapFlags));
+        assert_eq!(0, bsp.lump_version(LumpType::Planes));
+        assert_eq!(4, bsp.directories[LumpType::MapFlags].length);
+
+        let lump_file = bsp.lump_file(LumpType::MapFlags, Vec::new());
+        assert_eq!(42, lump_file.header.map_revision);
+        assert_eq!(3, lump_file.header.version);
+
+        // maps without the lump have no flags set
+        let bsp = Bsp::read(&test_map(Vec::new())).unwrap();
+        assert_eq!(MapFlags::empty(), bsp.map_flags);
+    }
+
+    #[test]
+    fn macro_textures() {
+        let data = test_map(vec![
+            (LumpType::Faces, [face(0, 0, -1), face(0, 0, -1)].concat()),
+            (
+                LumpType::FaceMacroTextureInfo,
+                [1u16, u16::MAX]
+                    .iter()
+                    .flat_map(|index| index.to_le_bytes())
+                    .collect(),
+            ),
+            (LumpType::TextureDataStringTable, bytes(&[0, 5])),
+            (LumpType::TextureDataStringData, b"base\0macro\0".to_vec()),
+        ]);
+        let bsp = Bsp::read(&data).unwrap();
+
+        assert_eq!(Some("macro"), bsp.face(0).unwrap().macro_texture());
+        assert_eq!(None, bsp.face(1).unwrap().macro_texture());
+    }
+
+    #[test]
+    fn lumps_read_into_their_fields() {
+        // every lump has a different number of elements, with and without the rayon feature
+        let data = test_map(vec![
+            (LumpType::Vertices, vec![0; 2 * 12]),
+            (LumpType::Edges, vec![0; 3 * 4]),
+            (LumpType::SurfaceEdges, vec![0; 4 * 4]),
+            (LumpType::Faces, face(0, 0, -1).repeat(5)),
+            (LumpType::LeafFaces, vec![0; 6 * 2]),
+            (LumpType::CubeMaps, vec![0; 7 * 16]),
+            (LumpType::Lighting, vec![0; 8 * 4]),
+            (LumpType::DisplacementLightMapAlphas, vec![0; 9]),
+            (LumpType::TextureDataStringData, vec![b'a'; 10]),
+        ]);
+        let bsp = Bsp::read(&data).unwrap();
+        let lengths = [
+            bsp.planes.len(),
+            bsp.vertices.len(),
+            bsp.edges.len(),
+            bsp.surface_edges.len(),
+            bsp.faces.len(),
+            bsp.leaf_faces.len(),
+            bsp.cubemaps.len(),
+            bsp.lighting.len(),
+            bsp.displacement_lightmap_alphas.len(),
+            bsp.texture_string_data.len(),
+        ];
+        assert_eq!([1, 2, 3, 4, 5, 6, 7, 8, 9, 10], lengths);
+
+        // the result doesn't depend on the number of threads used to read the lumps
+        #[cfg(feature = "rayon")]
+        {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap();
+            let sequential = pool.install(|| Bsp::read(&data).unwrap());
+            assert_eq!(format!("{:?}", sequential), format!("{:?}", bsp));
+        }
+    }
+
+    #[test]
+    fn game_lump_file() {
+        // a game lump header with a single lump, followed by the data of the lump
+        let mut game_lump = bytes(&[1, i32::from_be_bytes(*b"test")]);
+        game_lump.extend_from_slice(&[0, 0, 1, 0]);
+        // the offset is relative to the start of the lump file, including its 20 byte header
+        game_lump.extend_from_slice(&bytes(&[20 + 20, 8]));
+        game_lump.extend_from_slice(b"lumpdata");
+
+        let data = test_map(Vec::new());
+        let bsp = Bsp::read(&data).unwrap();
+        let lump_file = bsp.lump_file(LumpType::GameLump, game_lump);
+
+        let bsp = Bsp::read_with(&data, ReadOptions::default().lump_file(lump_file)).unwrap();
+        let game_lumps: Vec<_> = bsp.game_lumps().collect();
+        assert_eq!(1, game_lumps.len());
+        assert_eq!(*b"test", game_lumps[0].id);
+        assert_eq!(b"lumpdata", game_lumps[0].data().unwrap().as_ref());
+    }
+
+    #[test]
+    fn cluster_zero_is_visible() {
+        let leaf = |cluster: i16| {
+            let mut leaf = vec![0; 32];
+            leaf[4..6].copy_from_slice(&cluster.to_le_bytes());
+            leaf
+        };
+        // cluster 0 sees both clusters, cluster 1 only sees cluster 0
+        let mut vis = bytes(&[2, 20, 20, 21, 20]);
+        vis.extend_from_slice(&[0b11, 0b01]);
+
+        let data = test_map(vec![
+            (LumpType::Leaves, [leaf(0), leaf(1)].concat()),
+            (LumpType::Visibility, vis),
+        ]);
+        let bsp = Bsp::read(&data).unwrap();
+        let clusters = |leaf: usize| {
+            let leaf = bsp.leaf(leaf).unwrap();
+            let mut clusters: Vec<_> = leaf
+                .visible_set()
+                .unwrap()
+                .map(|leaf| leaf.cluster)
+                .collect();
+            clusters.sort();
+            clusters
+        };
+
+        assert_eq!(vec![0, 1], clusters(0));
+        assert_eq!(vec![0, 1], clusters(1));
+    }
 }
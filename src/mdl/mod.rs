@@ -0,0 +1,288 @@
+//! Loading of studio models (`.mdl`, `.vvd` and `.vtx`) for static props
+
+pub mod studio;
+pub mod vtx;
+pub mod vvd;
+
+use self::studio::*;
+pub use self::vvd::ModelVertex;
+use crate::data::Packfile;
+use crate::BspResult;
+use binrw::BinResult;
+use std::cmp::min;
+use std::io::Cursor;
+
+/// A studio model with the triangles of the default body groups at the highest level of detail
+#[derive(Debug, Clone)]
+pub struct Model {
+    pub name: String,
+    /// Directories to search for the textures in, relative to `materials/`
+    pub texture_dirs: Vec<String>,
+    pub textures: Vec<String>,
+    /// Skin families, mapping the material of a mesh to an index in `textures`
+    pub skins: Vec<Vec<u16>>,
+    pub vertices: Vec<ModelVertex>,
+    pub meshes: Vec<Mesh>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    /// Skin reference of the mesh, resolved to a texture using the model's skin families
+    pub material: i32,
+    /// Triangle list indexing into the model's vertices
+    pub indices: Vec<u32>,
+}
+
+impl Model {
+    /// Read a model from the contents of its `.mdl`, `.vvd` and `.vtx` files
+    pub fn read(mdl: &[u8], vvd: &[u8], vtx: &[u8]) -> BspResult<Self> {
+        Ok(Self::read_inner(mdl, vvd, vtx)?)
+    }
+
+    fn read_inner(mdl: &[u8], vvd: &[u8], vtx: &[u8]) -> BinResult<Self> {
+        let mut reader = Cursor::new(mdl);
+        let header: StudioHeader = read_at(&mut reader, 0)?;
+
+        let mut texture_dirs =
+            Vec::with_capacity(min(header.texture_dir_count.max(0) as usize, 1024));
+        for i in 0..header.texture_dir_count {
+            let offset: i32 =
+                read_at(&mut reader, header.texture_dir_offset as i64 + i as i64 * 4)?;
+            texture_dirs.push(normalize_path(&read_string_at(&mut reader, offset as i64)?));
+        }
+
+        let mut textures = Vec::with_capacity(min(header.texture_count.max(0) as usize, 1024));
+        for i in 0..header.texture_count {
+            let start = header.texture_offset as i64 + i as i64 * StudioTexture::SIZE as i64;
+            let texture: StudioTexture = read_at(&mut reader, start)?;
+            textures.push(normalize_path(&read_string_at(
+                &mut reader,
+                start + texture.name_offset as i64,
+            )?));
+        }
+
+        let mut skins = Vec::with_capacity(min(header.skin_family_count.max(0) as usize, 1024));
+        for family in 0..header.skin_family_count {
+            let mut skin =
+                Vec::with_capacity(min(header.skin_reference_count.max(0) as usize, 1024));
+            for reference in 0..header.skin_reference_count {
+                let index = family as i64 * header.skin_reference_count as i64 + reference as i64;
+                skin.push(read_at(
+                    &mut reader,
+                    header.skin_reference_offset as i64 + index * 2,
+                )?);
+            }
+            skins.push(skin);
+        }
+
+        let (_, vertices) = vvd::read_vertices(vvd)?;
+
+        let mut vtx_reader = Cursor::new(vtx);
+        let vtx_header: vtx::VtxHeader = read_at(&mut vtx_reader, 0)?;
+
+        let mut meshes = Vec::new();
+        for body_part_index in 0..header.body_part_count {
+            let body_part_start = header.body_part_offset as i64
+                + body_part_index as i64 * StudioBodyPart::SIZE as i64;
+            let body_part: StudioBodyPart = read_at(&mut reader, body_part_start)?;
+            if body_part.model_count < 1 {
+                continue;
+            }
+
+            // only the default model of each body part is used
+            let model_start = body_part_start + body_part.model_offset as i64;
+            let model: StudioModel = read_at(&mut reader, model_start)?;
+            let model_vertex_start = model.vertex_offset as u32 / ModelVertex::SIZE as u32;
+            let vtx_meshes = vtx::read_model_meshes(
+                &mut vtx_reader,
+                &vtx_header,
+                header.version,
+                body_part_index,
+                0,
+            )?;
+
+            for (mesh_index, vtx_indices) in (0..model.mesh_count).zip(vtx_meshes) {
+                let mesh: StudioMesh = read_at(
+                    &mut reader,
+                    model_start
+                        + model.mesh_offset as i64
+                        + mesh_index as i64 * StudioMesh::SIZE as i64,
+                )?;
+                let vertex_start = model_vertex_start.checked_add(mesh.vertex_offset as u32);
+                let indices = vtx_indices
+                    .into_iter()
+                    .map(|index| vertex_start?.checked_add(index))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| binrw::Error::AssertFail {
+                        pos: 0,
+                        message: "model vertex offset out of range".into(),
+                    })?;
+                if let Some(index) = indices
+                    .iter()
+                    .find(|index| **index as usize >= vertices.len())
+                {
+                    return Err(binrw::Error::AssertFail {
+                        pos: 0,
+                        message: format!(
+                            "model vertex {} out of range of the {} vertices",
+                            index,
+                            vertices.len()
+                        ),
+                    });
+                }
+                meshes.push(Mesh {
+                    material: mesh.material,
+                    indices,
+                });
+            }
+        }
+
+        Ok(Model {
+            name: header.name.as_str().into(),
+            texture_dirs,
+            textures,
+            skins,
+            vertices,
+            meshes,
+        })
+    }
+
+    /// Get the texture name for a mesh with a specific skin
+    ///
+    /// Falls back to the default skin if the skin doesn't exist
+    pub fn texture(&self, mesh: &Mesh, skin: usize) -> Option<&str> {
+        let family = self.skins.get(skin).or_else(|| self.skins.first())?;
+        let texture = *family.get(usize::try_from(mesh.material).ok()?)?;
+        self.textures.get(texture as usize).map(String::as_str)
+    }
+
+    /// Load a model and its vertex and mesh data from the packfile
+    ///
+    /// Returns `None` if any of the files for the model is not in the packfile
+    pub fn from_packfile(pack: &Packfile, path: &str) -> BspResult<Option<Self>> {
+        let base = path.strip_suffix(".mdl").unwrap_or(path);
+
+        let mdl = match pack.get(&format!("{}.mdl", base))? {
+            Some(mdl) => mdl,
+            None => return Ok(None),
+        };
+        let vvd = match pack.get(&format!("{}.vvd", base))? {
+            Some(vvd) => vvd,
+            None => return Ok(None),
+        };
+        let mut vtx = None;
+        for extension in ["dx90.vtx", "dx80.vtx", "sw.vtx", "vtx"] {
+            vtx = pack.get(&format!("{}.{}", base, extension))?;
+            if vtx.is_some() {
+                break;
+            }
+        }
+        let vtx = match vtx {
+            Some(vtx) => vtx,
+            None => return Ok(None),
+        };
+
+        Self::read(&mdl, &vvd, &vtx).map(Some)
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+/// The triangles of a static prop placed in the world
+#[derive(Debug, Clone)]
+pub struct PropModel {
+    pub vertices: Vec<ModelVertex>,
+    pub meshes: Vec<PropMesh>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PropMesh {
+    /// Path of the material, relative to `materials/` and without extension
+    pub material: String,
+    /// Triangle list indexing into the prop's vertices
+    pub indices: Vec<u32>,
+}
+
+#[test]
+fn test_read_model() {
+    fn put(data: &mut [u8], offset: usize, bytes: &[u8]) {
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+    fn put_i32s(data: &mut [u8], offset: usize, values: &[i32]) {
+        for (i, value) in values.iter().enumerate() {
+            put(data, offset + i * 4, &value.to_le_bytes());
+        }
+    }
+
+    let mdl = |version: i32| {
+        let mut mdl = vec![0; 1024];
+        put(&mut mdl, 0, b"IDST");
+        put_i32s(&mut mdl, 4, &[version]);
+        put(&mut mdl, 12, b"test.mdl");
+        // textures, texture dirs, skins and body parts
+        put_i32s(&mut mdl, 204, &[1, 400, 1, 480, 1, 1, 490, 1, 500]);
+        put_i32s(&mut mdl, 400, &[500]);
+        put(&mut mdl, 900, b"Tex\0");
+        put_i32s(&mut mdl, 480, &[920]);
+        put(&mut mdl, 920, b"Models\\Props\\\0");
+        put_i32s(&mut mdl, 500, &[0, 1, 1, 16]);
+        // model at 516 with a single mesh at 664
+        put_i32s(&mut mdl, 588, &[1, 148, 3, 0]);
+        put_i32s(&mut mdl, 664, &[0, 0, 3, 0]);
+        mdl
+    };
+
+    let mut vvd = vec![0; 64 + 3 * ModelVertex::SIZE];
+    put(&mut vvd, 0, b"IDSV");
+    put_i32s(&mut vvd, 4, &[4, 0, 1, 3]);
+    put_i32s(&mut vvd, 48, &[0, 0, 64]);
+    for i in 0..3 {
+        put(
+            &mut vvd,
+            64 + i * ModelVertex::SIZE + 16,
+            &(i as f32).to_le_bytes(),
+        );
+    }
+
+    // two strips, so the strip size for the version matters
+    let vtx = |version: i32| {
+        let mut vtx = vec![0; 512];
+        put_i32s(&mut vtx, 0, &[7]);
+        put_i32s(&mut vtx, 28, &[1, 36, 1, 8, 1, 8, 1, 12]);
+        // mesh at 64 with a strip group at 73
+        put_i32s(&mut vtx, 64, &[1, 9]);
+        put_i32s(&mut vtx, 73, &[3, 100, 6, 130, 2, 150]);
+        for i in 0..3 {
+            put(&mut vtx, 173 + i * 9 + 4, &(i as u16).to_le_bytes());
+        }
+        for (i, index) in [0u16, 1, 2, 2, 1, 0].iter().enumerate() {
+            put(&mut vtx, 203 + i * 2, &index.to_le_bytes());
+        }
+        for (strip, start) in [(0, 223), (1, 223 + vtx::VtxStrip::size(version))] {
+            put_i32s(&mut vtx, start, &[3, strip * 3, 3, 0]);
+            put(
+                &mut vtx,
+                start + 18,
+                &[vtx::VtxStripFlags::TRIANGLE_LIST.bits()],
+            );
+        }
+        vtx
+    };
+
+    for version in [48, 49] {
+        let model = Model::read(&mdl(version), &vvd, &vtx(version)).unwrap();
+        assert_eq!("test.mdl", model.name);
+        assert_eq!(vec!["Models/Props/".to_string()], model.texture_dirs);
+        assert_eq!(3, model.vertices.len());
+        assert_eq!(2.0, model.vertices[2].position.x);
+        assert_eq!(1, model.meshes.len());
+        assert_eq!(vec![0, 1, 2, 2, 1, 0], model.meshes[0].indices);
+        assert_eq!(Some("Tex"), model.texture(&model.meshes[0], 0));
+    }
+
+    // vertices outside of the vvd are rejected
+    put_i32s(&mut vvd, 16, &[2]);
+    assert!(Model::read(&mdl(48), &vvd, &vtx(48)).is_err());
+}
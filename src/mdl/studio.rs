@@ -0,0 +1,112 @@
+use crate::{FixedString, Vector};
+use binrw::{BinRead, BinReaderExt, BinResult, NullString};
+use std::io::{Cursor, Seek, SeekFrom};
+
+/// Header of the `.mdl` file
+#[derive(Debug, Clone, BinRead)]
+#[br(little, magic = b"IDST")]
+pub struct StudioHeader {
+    #[br(assert((44..=49).contains(&version), "unsupported mdl version {}", version))]
+    pub version: i32,
+    pub checksum: i32,
+    pub name: FixedString<64>,
+    pub data_length: i32,
+    pub eye_position: Vector,
+    pub illumination_position: Vector,
+    pub hull_min: Vector,
+    pub hull_max: Vector,
+    pub view_min: Vector,
+    pub view_max: Vector,
+    pub flags: i32,
+    // bones, bone controllers, hitboxes, animations, sequences and activities
+    #[br(pad_before = 48)]
+    pub texture_count: i32,
+    pub texture_offset: i32,
+    pub texture_dir_count: i32,
+    pub texture_dir_offset: i32,
+    pub skin_reference_count: i32,
+    pub skin_family_count: i32,
+    pub skin_reference_offset: i32,
+    pub body_part_count: i32,
+    pub body_part_offset: i32,
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct StudioTexture {
+    pub name_offset: i32,
+    // flags, used and runtime pointers
+    #[br(pad_after = 56)]
+    pub flags: i32,
+}
+
+impl StudioTexture {
+    pub const SIZE: usize = 64;
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct StudioBodyPart {
+    pub name_offset: i32,
+    pub model_count: i32,
+    pub base: i32,
+    pub model_offset: i32,
+}
+
+impl StudioBodyPart {
+    pub const SIZE: usize = 16;
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct StudioModel {
+    #[br(pad_before = 72)]
+    pub mesh_count: i32,
+    pub mesh_offset: i32,
+    pub vertex_count: i32,
+    /// Offset of the first vertex in the vvd vertex data, in bytes
+    #[br(pad_after = 60)]
+    pub vertex_offset: i32,
+}
+
+impl StudioModel {
+    pub const SIZE: usize = 148;
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct StudioMesh {
+    /// Skin reference for the mesh
+    pub material: i32,
+    pub model_offset: i32,
+    pub vertex_count: i32,
+    /// Index of the first vertex of the mesh, relative to the vertices of the model
+    #[br(pad_after = 100)]
+    pub vertex_offset: i32,
+}
+
+impl StudioMesh {
+    pub const SIZE: usize = 116;
+}
+
+pub fn read_at<T: BinRead>(reader: &mut Cursor<&[u8]>, offset: i64) -> BinResult<T>
+where
+    for<'a> T::Args<'a>: Default,
+{
+    reader.seek(SeekFrom::Start(offset as u64))?;
+    reader.read_le()
+}
+
+pub fn read_string_at(reader: &mut Cursor<&[u8]>, offset: i64) -> BinResult<String> {
+    Ok(read_at::<NullString>(reader, offset)?.to_string())
+}
+
+#[test]
+fn test_studio_sizes() {
+    use crate::data::test_read_size;
+
+    test_read_size::<StudioTexture>(StudioTexture::SIZE);
+    test_read_size::<StudioBodyPart>(StudioBodyPart::SIZE);
+    test_read_size::<StudioModel>(StudioModel::SIZE);
+    test_read_size::<StudioMesh>(StudioMesh::SIZE);
+}
@@ -0,0 +1,249 @@
+use super::studio::read_at;
+use crate::data::strip_to_triangles;
+use binrw::{BinRead, BinResult};
+use bitflags::bitflags;
+use std::cmp::min;
+use std::io::Cursor;
+
+/// Header of the `.vtx` file
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct VtxHeader {
+    #[br(assert(version == 7, "unsupported vtx version {}", version))]
+    pub version: i32,
+    pub vertex_cache_size: i32,
+    pub max_bones_per_strip: u16,
+    pub max_bones_per_triangle: u16,
+    pub max_bones_per_vertex: i32,
+    pub checksum: i32,
+    pub lod_count: i32,
+    pub material_replacement_offset: i32,
+    pub body_part_count: i32,
+    pub body_part_offset: i32,
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct VtxBodyPart {
+    pub model_count: i32,
+    pub model_offset: i32,
+}
+
+impl VtxBodyPart {
+    pub const SIZE: usize = 8;
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct VtxModel {
+    pub lod_count: i32,
+    pub lod_offset: i32,
+}
+
+impl VtxModel {
+    pub const SIZE: usize = 8;
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct VtxModelLod {
+    pub mesh_count: i32,
+    pub mesh_offset: i32,
+    pub switch_point: f32,
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct VtxMesh {
+    pub strip_group_count: i32,
+    pub strip_group_offset: i32,
+    pub flags: u8,
+}
+
+impl VtxMesh {
+    pub const SIZE: usize = 9;
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct VtxStripGroup {
+    pub vertex_count: i32,
+    pub vertex_offset: i32,
+    pub index_count: i32,
+    pub index_offset: i32,
+    pub strip_count: i32,
+    pub strip_offset: i32,
+    pub flags: u8,
+}
+
+impl VtxStripGroup {
+    pub const SIZE: usize = 25;
+
+    /// Get the size of a strip group for a version of the mdl file
+    ///
+    /// Version 49 adds the topology index count and offset
+    pub fn size(mdl_version: i32) -> usize {
+        if mdl_version >= 49 {
+            Self::SIZE + 8
+        } else {
+            Self::SIZE
+        }
+    }
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct VtxStrip {
+    pub index_count: i32,
+    pub index_offset: i32,
+    pub vertex_count: i32,
+    pub vertex_offset: i32,
+    pub bone_count: i16,
+    pub flags: VtxStripFlags,
+    pub bone_state_change_count: i32,
+    pub bone_state_change_offset: i32,
+}
+
+impl VtxStrip {
+    pub const SIZE: usize = 27;
+
+    /// Get the size of a strip for a version of the mdl file
+    ///
+    /// Version 49 adds the topology index count and offset
+    pub fn size(mdl_version: i32) -> usize {
+        if mdl_version >= 49 {
+            Self::SIZE + 8
+        } else {
+            Self::SIZE
+        }
+    }
+}
+
+bitflags! {
+    #[derive(BinRead)]
+    pub struct VtxStripFlags: u8 {
+        const TRIANGLE_LIST  = 0x01;
+        const TRIANGLE_STRIP = 0x02;
+    }
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct VtxVertex {
+    pub bone_weight_index: [u8; 3],
+    pub bone_count: u8,
+    /// Index of the vertex, relative to the vertices of the mesh
+    pub original_mesh_vertex: u16,
+    pub bone_id: [u8; 3],
+}
+
+impl VtxVertex {
+    pub const SIZE: usize = 9;
+}
+
+/// Read the triangles for all meshes of a model at the highest level of detail
+///
+/// The returned indexes are relative to the vertices of each mesh
+pub fn read_model_meshes(
+    reader: &mut Cursor<&[u8]>,
+    header: &VtxHeader,
+    mdl_version: i32,
+    body_part: i32,
+    model: i32,
+) -> BinResult<Vec<Vec<u32>>> {
+    let body_part_start =
+        header.body_part_offset as i64 + body_part as i64 * VtxBodyPart::SIZE as i64;
+    let body_part: VtxBodyPart = read_at(reader, body_part_start)?;
+    let model_start =
+        body_part_start + body_part.model_offset as i64 + model as i64 * VtxModel::SIZE as i64;
+    let model: VtxModel = read_at(reader, model_start)?;
+    let lod_start = model_start + model.lod_offset as i64;
+    let lod: VtxModelLod = read_at(reader, lod_start)?;
+
+    let mut meshes = Vec::with_capacity(min(lod.mesh_count.max(0) as usize, 1024));
+    for mesh_index in 0..lod.mesh_count {
+        let mesh_start =
+            lod_start + lod.mesh_offset as i64 + mesh_index as i64 * VtxMesh::SIZE as i64;
+        let mesh: VtxMesh = read_at(reader, mesh_start)?;
+        let mut indices = Vec::new();
+
+        for group_index in 0..mesh.strip_group_count {
+            let group_start = mesh_start
+                + mesh.strip_group_offset as i64
+                + group_index as i64 * VtxStripGroup::size(mdl_version) as i64;
+            let group: VtxStripGroup = read_at(reader, group_start)?;
+
+            let mut vertices = Vec::with_capacity(min(group.vertex_count.max(0) as usize, 1024));
+            for i in 0..group.vertex_count {
+                let vertex: VtxVertex = read_at(
+                    reader,
+                    group_start + group.vertex_offset as i64 + i as i64 * VtxVertex::SIZE as i64,
+                )?;
+                vertices.push(vertex.original_mesh_vertex as u32);
+            }
+            let mut group_indices =
+                Vec::with_capacity(min(group.index_count.max(0) as usize, 1024));
+            for i in 0..group.index_count {
+                let index: u16 = read_at(
+                    reader,
+                    group_start + group.index_offset as i64 + i as i64 * 2,
+                )?;
+                group_indices.push(index as usize);
+            }
+            let vertex = |index: usize| -> BinResult<u32> {
+                vertices
+                    .get(index)
+                    .copied()
+                    .ok_or(binrw::Error::AssertFail {
+                        pos: group_start as u64,
+                        message: format!("vtx index {} out of range", index),
+                    })
+            };
+
+            for strip_index in 0..group.strip_count {
+                let strip: VtxStrip = read_at(
+                    reader,
+                    group_start
+                        + group.strip_offset as i64
+                        + strip_index as i64 * VtxStrip::size(mdl_version) as i64,
+                )?;
+                let start = strip.index_offset.max(0) as usize;
+                let end = start + strip.index_count.max(0) as usize;
+                let strip_indices =
+                    group_indices
+                        .get(start..end)
+                        .ok_or(binrw::Error::AssertFail {
+                            pos: group_start as u64,
+                            message: "vtx strip out of range".into(),
+                        })?;
+
+                if strip.flags.contains(VtxStripFlags::TRIANGLE_STRIP) {
+                    for triangle in strip_to_triangles(strip_indices) {
+                        for index in triangle {
+                            indices.push(vertex(index)?);
+                        }
+                    }
+                } else {
+                    for triangle in strip_indices.chunks_exact(3) {
+                        for index in triangle {
+                            indices.push(vertex(*index)?);
+                        }
+                    }
+                }
+            }
+        }
+        meshes.push(indices);
+    }
+
+    Ok(meshes)
+}
+
+#[test]
+fn test_vtx_sizes() {
+    use crate::data::test_read_size;
+
+    test_read_size::<VtxMesh>(VtxMesh::SIZE);
+    test_read_size::<VtxStripGroup>(VtxStripGroup::SIZE);
+    test_read_size::<VtxStrip>(VtxStrip::SIZE);
+    test_read_size::<VtxVertex>(VtxVertex::SIZE);
+}
@@ -0,0 +1,96 @@
+use super::studio::read_at;
+use crate::Vector;
+use binrw::{BinRead, BinResult};
+use std::io::Cursor;
+
+/// Header of the `.vvd` file
+#[derive(Debug, Clone, BinRead)]
+#[br(little, magic = b"IDSV")]
+pub struct VertexFileHeader {
+    #[br(assert(version == 4, "unsupported vvd version {}", version))]
+    pub version: i32,
+    pub checksum: i32,
+    pub lod_count: i32,
+    pub lod_vertex_count: [i32; 8],
+    pub fixup_count: i32,
+    pub fixup_offset: i32,
+    pub vertex_offset: i32,
+    pub tangent_offset: i32,
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(little)]
+pub struct VertexFileFixup {
+    pub lod: i32,
+    pub source_vertex: i32,
+    pub vertex_count: i32,
+}
+
+impl VertexFileFixup {
+    pub const SIZE: usize = 12;
+}
+
+/// Vertex of a studio model
+#[derive(Debug, Clone, Copy, BinRead)]
+#[br(little)]
+pub struct ModelVertex {
+    // bone weights
+    #[br(pad_before = 16)]
+    pub position: Vector,
+    pub normal: Vector,
+    pub uv: [f32; 2],
+}
+
+impl ModelVertex {
+    pub const SIZE: usize = 48;
+}
+
+/// Read the vertices for the highest level of detail
+pub fn read_vertices(data: &[u8]) -> BinResult<(VertexFileHeader, Vec<ModelVertex>)> {
+    let mut reader = Cursor::new(data);
+    let header: VertexFileHeader = read_at(&mut reader, 0)?;
+
+    let read_vertex = |reader: &mut Cursor<&[u8]>, index: i32| -> BinResult<ModelVertex> {
+        read_at(
+            reader,
+            header.vertex_offset as i64 + index as i64 * ModelVertex::SIZE as i64,
+        )
+    };
+
+    let mut vertices = Vec::new();
+    if header.fixup_count == 0 {
+        for i in 0..header.lod_vertex_count[0] {
+            vertices.push(read_vertex(&mut reader, i)?);
+        }
+    } else {
+        for fixup_index in 0..header.fixup_count {
+            let fixup: VertexFileFixup = read_at(
+                &mut reader,
+                header.fixup_offset as i64 + fixup_index as i64 * VertexFileFixup::SIZE as i64,
+            )?;
+            // fixups apply to all lods up to and including the fixup's lod
+            if fixup.lod >= 0 {
+                let end = fixup
+                    .source_vertex
+                    .checked_add(fixup.vertex_count)
+                    .ok_or_else(|| binrw::Error::AssertFail {
+                        pos: header.fixup_offset as u64,
+                        message: "vvd fixup out of range".into(),
+                    })?;
+                for i in fixup.source_vertex..end {
+                    vertices.push(read_vertex(&mut reader, i)?);
+                }
+            }
+        }
+    }
+
+    Ok((header, vertices))
+}
+
+#[test]
+fn test_vvd_sizes() {
+    use crate::data::test_read_size;
+
+    test_read_size::<VertexFileFixup>(VertexFileFixup::SIZE);
+    test_read_size::<ModelVertex>(ModelVertex::SIZE);
+}
@@ -0,0 +1,38 @@
+use crate::data::{LightingMode, LumpFile};
+
+/// Options for reading a bsp file
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    /// Which variant of the faces, lighting and world lights to load
+    ///
+    /// Falls back to the other variant if the bsp doesn't contain the requested one
+    pub lighting: LightingMode,
+    /// Lump files to load in place of the lumps stored in the bsp
+    pub lump_files: Vec<LumpFile>,
+    /// Keep reading when encountering invalid data
+    ///
+    /// Lumps that can't be read are left empty and invalid references are removed or clamped,
+    /// with a warning added to [`Bsp::warnings`](crate::Bsp::warnings) for every problem
+    pub lenient: bool,
+}
+
+impl ReadOptions {
+    pub fn lighting(mut self, lighting: LightingMode) -> Self {
+        self.lighting = lighting;
+        self
+    }
+
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Load a lump from a lump file instead of from the bsp
+    ///
+    /// If multiple lump files replace the same lump, the last one is used
+    pub fn lump_file(mut self, lump_file: LumpFile) -> Self {
+        self.lump_files.push(lump_file);
+        self
+    }
+}
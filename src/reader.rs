@@ -1,5 +1,7 @@
 use crate::*;
+use binrw::io::Cursor;
 use binrw::BinReaderExt;
+use bytemuck::Pod;
 use std::borrow::Cow;
 use std::fmt::Debug;
 use std::mem::size_of;
@@ -31,6 +33,25 @@ impl<'a> LumpReader<Cursor<Cow<'a, [u8]>>> {
     }
 }
 
+impl LumpReader<Cursor<Cow<'_, [u8]>>> {
+    /// Read a list of plain data items
+    ///
+    /// If the lump data is suitably aligned, the bytes are used directly instead of decoding
+    /// every item
+    pub fn read_pod_vec<T>(&mut self) -> BspResult<Vec<T>>
+    where
+        T: Pod + BinRead + Debug,
+        T::Args<'static>: Default,
+    {
+        // the in-memory layout only matches the file on little endian targets
+        #[cfg(target_endian = "little")]
+        if let Ok(items) = bytemuck::try_cast_slice::<u8, T>(self.inner.get_ref()) {
+            return Ok(items.to_vec());
+        }
+        self.read_vec(|r| r.read())
+    }
+}
+
 impl<R: BinReaderExt + Read> LumpReader<R> {
     pub fn read_entities(&mut self) -> BspResult<Entities> {
         let mut entities = String::with_capacity(self.length);
@@ -43,7 +64,7 @@ impl<R: BinReaderExt + Read> LumpReader<R> {
     where
         F: FnMut(&mut LumpReader<R>) -> BspResult<T>,
     {
-        if self.length % size_of::<T>() != 0 {
+        if !self.length.is_multiple_of(size_of::<T>()) {
             return Err(BspError::InvalidLumpSize {
                 lump: self.lump,
                 element_size: size_of::<T>(),
@@ -65,7 +86,7 @@ impl<R: BinReaderExt + Read> LumpReader<R> {
     where
         F: FnMut(&mut LumpReader<R>, Version) -> BspResult<T>,
     {
-        if self.length % size_of::<T>() != 0 {
+        if !self.length.is_multiple_of(size_of::<T>()) {
             return Err(BspError::InvalidLumpSize {
                 lump: self.lump,
                 element_size: size_of::<T>(),
@@ -106,23 +127,127 @@ impl<R: BinReaderExt + Read> LumpReader<R> {
         Ok(result?)
     }
 
+    pub fn read_occlusion(&mut self) -> BspResult<Occlusion> {
+        if self.length == 0 {
+            return Ok(Occlusion::default());
+        }
+
+        self.read_args((self.version,))
+    }
+
+    /// Read a number of bytes, checking the size against the remaining lump data before allocating
+    fn read_bytes(&mut self, size: usize) -> BspResult<Vec<u8>> {
+        let remaining = (self.length as u64).saturating_sub(self.inner.stream_position()?);
+        if size as u64 > remaining {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        let mut data = vec![0; size];
+        self.inner.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    pub fn read_physics_models(&mut self) -> BspResult<Vec<PhysicsModel>> {
+        let mut models = Vec::new();
+
+        while self.inner.stream_position()? + size_of::<PhysicsModelHeader>() as u64
+            <= self.length as u64
+        {
+            let header: PhysicsModelHeader = self.inner.read_le()?;
+            if header.model_index == -1 {
+                break;
+            }
+
+            let mut solids = Vec::with_capacity(min(header.solid_count.max(0) as usize, 1024));
+            for _ in 0..header.solid_count {
+                let size: i32 = self.inner.read_le()?;
+                let data = self.read_bytes(size.max(0) as usize)?;
+                solids.push(PhysicsSolid { data });
+            }
+
+            let key_values = self.read_bytes(header.key_data_size.max(0) as usize)?;
+            let key_values = String::from_utf8_lossy(&key_values)
+                .trim_end_matches('\0')
+                .to_string();
+
+            models.push(PhysicsModel {
+                model_index: header.model_index,
+                solids,
+                key_values,
+            });
+        }
+
+        Ok(models)
+    }
+
+    pub fn read_world_lights(&mut self) -> BspResult<Vec<WorldLight>> {
+        let element_size = WorldLight::size(self.version);
+        if !self.length.is_multiple_of(element_size) {
+            return Err(BspError::InvalidLumpSize {
+                lump: self.lump,
+                element_size,
+                lump_size: self.length,
+            });
+        }
+
+        let mut lights = Vec::with_capacity(self.length / element_size);
+        for _ in 0..(self.length / element_size) {
+            lights.push(self.read_args((self.version,))?);
+        }
+        Ok(lights)
+    }
+
+    pub fn read_displacement_physics(&mut self) -> BspResult<Vec<PhysicsSolid>> {
+        if self.length < size_of::<u16>() {
+            return Ok(Vec::new());
+        }
+
+        let count: u16 = self.inner.read_le()?;
+        let mut sizes = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            sizes.push(self.inner.read_le::<u16>()?);
+        }
+
+        let mut solids = Vec::with_capacity(count as usize);
+        for size in sizes {
+            let mut data = vec![0; size as usize];
+            self.inner.read_exact(&mut data)?;
+            solids.push(PhysicsSolid { data });
+        }
+
+        Ok(solids)
+    }
+
     pub fn read_visdata(&mut self) -> BspResult<VisData> {
         if self.length < size_of::<u32>() * 2 {
             return Ok(VisData::default());
         }
 
-        let cluster_count = self.inner.read_le()?;
-        let mut pvs_offsets = Vec::with_capacity(min(cluster_count as usize, 1024));
-        let mut pas_offsets = Vec::with_capacity(min(cluster_count as usize, 1024));
+        let cluster_count: u32 = self.inner.read_le()?;
+        let mut pvs_offsets: Vec<i32> = Vec::with_capacity(min(cluster_count as usize, 1024));
+        let mut pas_offsets: Vec<i32> = Vec::with_capacity(min(cluster_count as usize, 1024));
 
         for _ in 0..cluster_count {
             pvs_offsets.push(self.inner.read_le()?);
             pas_offsets.push(self.inner.read_le()?);
         }
 
+        let header_size = self.inner.stream_position()? as i64;
         let mut data = Vec::new();
         self.inner.read_to_end(&mut data)?;
 
+        // the offsets are relative to the start of the lump, make them relative to the data instead
+        let clusters = pvs_offsets.iter_mut().enumerate();
+        for (cluster, offset) in clusters.chain(pas_offsets.iter_mut().enumerate()) {
+            let relative = *offset as i64 - header_size;
+            if relative < 0 || relative >= data.len() as i64 {
+                return Err(BspError::VisDataOutOfBounds {
+                    cluster,
+                    offset: *offset,
+                });
+            }
+            *offset = relative as i32;
+        }
+
         Ok(VisData {
             cluster_count,
             pvs_offsets,
@@ -131,3 +256,39 @@ impl<R: BinReaderExt + Read> LumpReader<R> {
         })
     }
 }
+
+#[test]
+fn test_read_pod_vec() {
+    let bytes: Vec<u8> = (0..41).collect();
+
+    fn read(data: &[u8]) -> LumpReader<Cursor<Cow<'_, [u8]>>> {
+        LumpReader::new(Cow::Borrowed(data), 0, LumpType::Planes)
+    }
+
+    // both the aligned and unaligned data should give the same result as decoding every item
+    for data in [&bytes[..40], &bytes[1..41]] {
+        let pod: Vec<Plane> = read(data).read_pod_vec().unwrap();
+        let decoded: Vec<Plane> = read(data).read_vec(|r| r.read()).unwrap();
+        assert_eq!(2, pod.len());
+        for (pod, decoded) in pod.iter().zip(decoded.iter()) {
+            assert_eq!(pod.ty, decoded.ty);
+            assert_eq!(pod.normal, decoded.normal);
+        }
+    }
+
+    assert!(read(&bytes[..30]).read_pod_vec::<Plane>().is_err());
+}
+
+#[test]
+fn test_read_physics_models_size() {
+    let mut bytes = Vec::new();
+    // model 0 with a single solid claiming to be 2 GiB
+    for value in [0, 0, 0, 1, i32::MAX] {
+        bytes.extend_from_slice(&i32::to_le_bytes(value));
+    }
+    let mut reader = LumpReader::new(Cow::Borrowed(&bytes), 0, LumpType::PhysCollide);
+    assert!(matches!(
+        reader.read_physics_models(),
+        Err(BspError::IO(error)) if error.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+}
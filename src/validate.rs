@@ -0,0 +1,506 @@
+use crate::error::ValidationError;
+use crate::*;
+
+/// Reports validation errors, either failing on the first error or recording a warning
+struct Validator<'a> {
+    lenient: bool,
+    warnings: &'a mut Vec<BspWarning>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, lump: LumpType, element: usize, error: ValidationError) -> BspResult<()> {
+        if self.lenient {
+            self.warnings.push(BspWarning::InvalidElement {
+                lump,
+                element,
+                error,
+            });
+            Ok(())
+        } else {
+            Err(error.into())
+        }
+    }
+
+    /// Check that all indexes of the elements in a lump are in range of the target list
+    ///
+    /// In lenient mode, elements with an invalid index are passed to `repair`
+    fn references<T, I>(
+        &mut self,
+        (lump, source): (LumpType, &'static str),
+        elements: &mut [T],
+        (target, size): (&'static str, usize),
+        indexes: impl Fn(&T) -> I,
+        mut repair: impl FnMut(&mut T),
+    ) -> BspResult<()>
+    where
+        I: IntoIterator,
+        I::Item: Into<i64>,
+    {
+        for (element, item) in elements.iter_mut().enumerate() {
+            let invalid = indexes(item)
+                .into_iter()
+                .map(Into::into)
+                .find(|index| *index < 0 || *index >= size as i64);
+            if let Some(index) = invalid {
+                let error = ValidationError::ReferenceOutOfRange {
+                    source_: source,
+                    target,
+                    index,
+                    size,
+                };
+                self.report(lump, element, error)?;
+                repair(item);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check references that are clamped into range of the target list in lenient mode
+    ///
+    /// If the target list is empty there is nothing to clamp to and all elements are removed,
+    /// any lists referencing the elements need to be checked afterwards
+    fn clamped_references<T, I>(
+        &mut self,
+        source: (LumpType, &'static str),
+        elements: &mut Vec<T>,
+        (target, size): (&'static str, usize),
+        indexes: impl Fn(&T) -> I,
+        repair: impl FnMut(&mut T),
+    ) -> BspResult<()>
+    where
+        I: IntoIterator,
+        I::Item: Into<i64>,
+    {
+        self.references(source, elements, (target, size), indexes, repair)?;
+        if size == 0 {
+            elements.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Clamp an index into a list of `size` items
+fn clamp<T: TryFrom<usize> + Default>(size: usize) -> T {
+    T::try_from(size.saturating_sub(1)).unwrap_or_default()
+}
+
+impl Bsp {
+    /// Check that all references between the lumps are valid
+    ///
+    /// In lenient mode, invalid references are removed or clamped and a warning is recorded
+    pub(crate) fn validate(&mut self, lenient: bool) -> BspResult<()> {
+        let mut validator = Validator {
+            lenient,
+            warnings: &mut self.warnings,
+        };
+
+        for (element, face) in self.faces.iter_mut().enumerate() {
+            if face.displacement_index().is_some() && face.num_edges != 4 {
+                let error = ValidationError::NonSquareDisplacement(face.num_edges);
+                validator.report(LumpType::Faces, element, error)?;
+                face.displacement_info = -1;
+            }
+        }
+        validator.references(
+            (LumpType::Faces, "face"),
+            &mut self.faces,
+            ("displacement", self.displacements.len()),
+            |face| face.displacement_index(),
+            |face| face.displacement_info = -1,
+        )?;
+        validator.clamped_references(
+            (LumpType::DisplacementInfo, "displacement"),
+            &mut self.displacements,
+            ("face", self.faces.len()),
+            |displacement| [displacement.map_face],
+            |displacement| displacement.map_face = clamp(self.faces.len()),
+        )?;
+        for (element, displacement) in self.displacements.iter_mut().enumerate() {
+            if !(2..=4).contains(&displacement.power) {
+                let error = ValidationError::InvalidDisplacementPower(displacement.power);
+                validator.report(LumpType::DisplacementInfo, element, error)?;
+                displacement.power = 2;
+            }
+        }
+        validator.references(
+            (LumpType::DisplacementInfo, "displacement"),
+            &mut self.displacements,
+            ("displacement_vertex", self.displacement_vertices.len()),
+            |displacement| {
+                [
+                    displacement.displacement_vertex_start as i64,
+                    displacement.displacement_vertex_start as i64
+                        + displacement.vertex_count() as i64
+                        - 1,
+                ]
+            },
+            // an empty range of vertices
+            |displacement| {
+                displacement.displacement_vertex_start = self.displacement_vertices.len() as i32
+            },
+        )?;
+        validator.clamped_references(
+            (LumpType::Edges, "edge"),
+            &mut self.edges,
+            ("vertex", self.vertices.len()),
+            |edge| [edge.start_index, edge.end_index],
+            |edge| {
+                edge.start_index = edge.start_index.min(clamp(self.vertices.len()));
+                edge.end_index = edge.end_index.min(clamp(self.vertices.len()));
+            },
+        )?;
+        validator.clamped_references(
+            (LumpType::SurfaceEdges, "surface_edge"),
+            &mut self.surface_edges,
+            ("edge", self.edges.len()),
+            |edge| [edge.edge_index()],
+            |edge| edge.edge = 0,
+        )?;
+        validator.references(
+            (LumpType::Faces, "face"),
+            &mut self.faces,
+            ("surface_edge", self.surface_edges.len()),
+            |face| {
+                (face.num_edges > 0)
+                    .then(|| [face.first_edge, face.first_edge + face.num_edges as i32 - 1])
+                    .into_iter()
+                    .flatten()
+            },
+            |face| face.num_edges = 0,
+        )?;
+        let displacement_count = self.displacements.len();
+        validator.references(
+            (LumpType::DisplacementInfo, "displacement"),
+            &mut self.displacements,
+            ("displacement", displacement_count),
+            |displacement| {
+                displacement
+                    .corner_neighbours
+                    .iter()
+                    .flat_map(|corner| corner.neighbours())
+                    .collect::<Vec<_>>()
+            },
+            |displacement| {
+                for corner in &mut displacement.corner_neighbours {
+                    corner.neighbour_count = 0;
+                }
+            },
+        )?;
+        validator.references(
+            (LumpType::DisplacementInfo, "displacement"),
+            &mut self.displacements,
+            ("displacement", displacement_count),
+            |displacement| {
+                displacement
+                    .edge_neighbours
+                    .iter()
+                    .flat_map(|edge| edge.iter())
+                    .map(|sub| sub.neighbour_index)
+                    .collect::<Vec<_>>()
+            },
+            |displacement| {
+                for sub in displacement
+                    .edge_neighbours
+                    .iter_mut()
+                    .flat_map(|edge| &mut edge.sub_neighbours)
+                {
+                    if matches!(sub, Some(sub) if sub.neighbour_index as usize >= displacement_count)
+                    {
+                        *sub = None;
+                    }
+                }
+            },
+        )?;
+        validator.references(
+            (LumpType::Faces, "face"),
+            &mut self.faces,
+            ("texture_info", self.textures_info.len()),
+            |face| (face.texture_info >= 0).then_some(face.texture_info),
+            |face| face.texture_info = -1,
+        )?;
+        validator.references(
+            (LumpType::TextureInfo, "texture_info"),
+            &mut self.textures_info,
+            ("texture_data", self.textures_data.len()),
+            |texture| (texture.texture_data_index >= 0).then_some(texture.texture_data_index),
+            |texture| texture.texture_data_index = -1,
+        )?;
+        validator.references(
+            (LumpType::TextureData, "textures_data"),
+            &mut self.textures_data,
+            ("texture_string_tables", self.texture_string_tables.len()),
+            |texture| (texture.name_string_table_id >= 0).then_some(texture.name_string_table_id),
+            |texture| texture.name_string_table_id = -1,
+        )?;
+        validator.references(
+            (LumpType::TextureDataStringTable, "texture_string_tables"),
+            &mut self.texture_string_tables,
+            ("texture_string_data", self.texture_string_data.len()),
+            |offset| (*offset >= 0).then_some(*offset),
+            |offset| *offset = -1,
+        )?;
+        for (element, offset) in self.texture_string_tables.iter_mut().enumerate() {
+            // slicing the string data at the offset requires it to be at the start of a character
+            if *offset >= 0 && !self.texture_string_data.is_char_boundary(*offset as usize) {
+                let error = ValidationError::StringOffsetNotCharBoundary(*offset);
+                validator.report(LumpType::TextureDataStringTable, element, error)?;
+                *offset = -1;
+            }
+        }
+        validator.clamped_references(
+            (LumpType::Nodes, "node"),
+            &mut self.nodes,
+            ("plane", self.planes.len()),
+            |node| [node.plane_index],
+            |node| node.plane_index = clamp(self.planes.len()),
+        )?;
+        // leaf 0 is used to replace invalid children, which requires the leaf to exist
+        validator.clamped_references(
+            (LumpType::Nodes, "node"),
+            &mut self.nodes,
+            ("leaf", self.leaves.len()),
+            |node| {
+                node.children
+                    .into_iter()
+                    .filter_map(|index| (index < 0).then_some(!index))
+            },
+            |node| {
+                for child in &mut node.children {
+                    if *child < 0 && !*child >= self.leaves.len() as i32 {
+                        *child = -1;
+                    }
+                }
+            },
+        )?;
+        let node_count = self.nodes.len();
+        validator.references(
+            (LumpType::Models, "model"),
+            &mut self.models,
+            ("face", self.faces.len()),
+            |model| {
+                (model.face_count > 0)
+                    .then(|| {
+                        [
+                            model.first_face as i64,
+                            model.first_face as i64 + model.face_count as i64 - 1,
+                        ]
+                    })
+                    .into_iter()
+                    .flatten()
+            },
+            |model| model.face_count = 0,
+        )?;
+        validator.references(
+            (LumpType::Models, "model"),
+            &mut self.models,
+            ("node", node_count),
+            |model| [model.head_node],
+            |model| model.head_node = 0,
+        )?;
+        validator.references(
+            (LumpType::Nodes, "node"),
+            &mut self.nodes,
+            ("node", node_count),
+            |node| node.children.into_iter().filter(|index| *index >= 0),
+            |node| {
+                for child in &mut node.children {
+                    if *child >= node_count as i32 {
+                        // leaf 0 is always the solid leaf
+                        *child = -1;
+                    }
+                }
+            },
+        )?;
+
+        let mut visited = vec![false; node_count];
+        let roots: Vec<usize> = std::iter::once(0)
+            .chain(self.models.iter().map(|model| model.head_node as usize))
+            .collect();
+        for root in roots {
+            if root >= node_count || visited[root] {
+                continue;
+            }
+            visited[root] = true;
+            let mut stack = vec![root];
+            while let Some(parent) = stack.pop() {
+                for child in &mut self.nodes[parent].children {
+                    if *child < 0 {
+                        continue;
+                    }
+                    let node = *child as usize;
+                    if visited[node] {
+                        // walking the tree would never terminate
+                        validator.report(
+                            LumpType::Nodes,
+                            parent,
+                            ValidationError::NodeCycle(node),
+                        )?;
+                        *child = -1;
+                    } else {
+                        visited[node] = true;
+                        stack.push(node);
+                    }
+                }
+            }
+        }
+
+        validator.clamped_references(
+            (LumpType::LeafFaces, "leaf_face"),
+            &mut self.leaf_faces,
+            ("face", self.faces.len()),
+            |leaf_face| [leaf_face.face],
+            |leaf_face| leaf_face.face = clamp(self.faces.len()),
+        )?;
+        validator.references(
+            (LumpType::Leaves, "leaf"),
+            &mut self.leaves.leaves,
+            ("leaf_face", self.leaf_faces.len()),
+            |leaf| {
+                (leaf.leaf_face_count > 0)
+                    .then(|| leaf.first_leaf_face as i64 + leaf.leaf_face_count as i64 - 1)
+            },
+            |leaf| leaf.leaf_face_count = 0,
+        )?;
+        validator.clamped_references(
+            (LumpType::BrushSides, "brush_side"),
+            &mut self.brush_sides,
+            ("plane", self.planes.len()),
+            |side| [side.plane],
+            |side| side.plane = clamp(self.planes.len()),
+        )?;
+        validator.references(
+            (LumpType::Brushes, "brush"),
+            &mut self.brushes,
+            ("brush_side", self.brush_sides.len()),
+            |brush| {
+                (brush.num_brush_sides > 0)
+                    .then(|| brush.brush_side as i64 + brush.num_brush_sides as i64 - 1)
+            },
+            |brush| brush.num_brush_sides = 0,
+        )?;
+        validator.clamped_references(
+            (LumpType::LeafBrushes, "leaf_brush"),
+            &mut self.leaf_brushes,
+            ("brush", self.brushes.len()),
+            |leaf_brush| [leaf_brush.brush],
+            |leaf_brush| leaf_brush.brush = clamp(self.brushes.len()),
+        )?;
+        validator.references(
+            (LumpType::Leaves, "leaf"),
+            &mut self.leaves.leaves,
+            ("leaf_brush", self.leaf_brushes.len()),
+            |leaf| {
+                (leaf.leaf_brush_count > 0)
+                    .then(|| leaf.first_leaf_brush as i64 + leaf.leaf_brush_count as i64 - 1)
+            },
+            |leaf| leaf.leaf_brush_count = 0,
+        )?;
+        validator.references(
+            (LumpType::BrushSides, "brush_side"),
+            &mut self.brush_sides,
+            ("texture_info", self.textures_info.len()),
+            |side| (side.texture_info >= 0).then_some(side.texture_info),
+            |side| side.texture_info = -1,
+        )?;
+        validator.references(
+            (LumpType::BrushSides, "brush_side"),
+            &mut self.brush_sides,
+            ("displacement", self.displacements.len()),
+            |side| (side.displacement_info >= 0).then_some(side.displacement_info),
+            |side| side.displacement_info = -1,
+        )?;
+
+        if self.vis_data.cluster_count > 0 {
+            validator.references(
+                (LumpType::Leaves, "leaf"),
+                &mut self.leaves.leaves,
+                ("cluster", self.vis_data.cluster_count as usize),
+                |leaf| (leaf.cluster >= 0).then_some(leaf.cluster),
+                |leaf| leaf.cluster = -1,
+            )?;
+        }
+
+        let static_props = &mut self.static_props;
+        validator.clamped_references(
+            (LumpType::GameLump, "static prop leaves"),
+            &mut static_props.leaf.leaves,
+            ("leaf", self.leaves.len()),
+            |leaf| [*leaf],
+            |leaf| *leaf = clamp(self.leaves.len()),
+        )?;
+        validator.references(
+            (LumpType::GameLump, "static props"),
+            &mut static_props.props.props,
+            ("static prop leaves", static_props.leaf.leaves.len()),
+            |prop| {
+                (prop.leaf_count > 0).then(|| prop.first_leaf as i64 + prop.leaf_count as i64 - 1)
+            },
+            |prop| prop.leaf_count = 0,
+        )?;
+        validator.clamped_references(
+            (LumpType::GameLump, "static props"),
+            &mut static_props.props.props,
+            ("static prop models", static_props.dict.name.len()),
+            |prop| [prop.prop_type],
+            |prop| prop.prop_type = clamp(static_props.dict.name.len()),
+        )?;
+        static_props.leaf_index = StaticPropLeafIndex::new(&static_props.leaf, &static_props.props);
+
+        if self.nodes.is_empty() {
+            if lenient {
+                self.warnings
+                    .push(BspWarning::Validation(ValidationError::NoRootNode));
+            } else {
+                return Err(ValidationError::NoRootNode.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_lenient_references() {
+    let mut edges = vec![
+        Edge {
+            start_index: 0,
+            end_index: 1,
+        },
+        Edge {
+            start_index: 1,
+            end_index: 5,
+        },
+    ];
+    let check = |validator: &mut Validator, edges: &mut [Edge]| {
+        validator.references(
+            (LumpType::Edges, "edge"),
+            edges,
+            ("vertex", 2),
+            |edge| [edge.start_index, edge.end_index],
+            |edge| edge.end_index = 1,
+        )
+    };
+
+    let mut warnings = Vec::new();
+    let mut strict = Validator {
+        lenient: false,
+        warnings: &mut warnings,
+    };
+    assert!(check(&mut strict, &mut edges).is_err());
+    assert_eq!(5, edges[1].end_index);
+
+    let mut lenient = Validator {
+        lenient: true,
+        warnings: &mut warnings,
+    };
+    assert!(check(&mut lenient, &mut edges).is_ok());
+    assert_eq!(1, edges[1].end_index);
+    assert!(matches!(
+        warnings.as_slice(),
+        [BspWarning::InvalidElement {
+            lump: LumpType::Edges,
+            element: 1,
+            error: ValidationError::ReferenceOutOfRange { index: 5, .. },
+        }]
+    ));
+}